@@ -0,0 +1,352 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult};
+use cw2::set_contract_version;
+
+use crate::error::ContractError;
+use crate::execute::{self, INSTANTIATE_DISBURSER_REPLY_ID};
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::query;
+use crate::state::{Config, CONFIG};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:drip-disburser-factory";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    // address normalization
+    let admin = match &msg.admin {
+        Some(a) => deps.api.addr_validate(a)?,
+        None => info.sender.clone(),
+    };
+
+    // assemble and store config
+    let cfg = Config {
+        admin,
+        disburser_code_id: msg.disburser_code_id,
+        oracle_addr: deps.api.addr_validate(&msg.oracle_addr)?,
+        payout_duration_bounds: msg.payout_duration_bounds,
+    };
+    CONFIG.save(deps.storage, &cfg)?;
+
+    // version for migrations
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attribute("action", "instantiate"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::CreateDisburser { template, label } => {
+            execute::create_disburser(deps, env, info, template, label)
+        }
+        ExecuteMsg::UpdateConfig {
+            admin,
+            disburser_code_id,
+            oracle_addr,
+            payout_duration_bounds,
+        } => execute::update_config(deps, info, admin, disburser_code_id, oracle_addr, payout_duration_bounds),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        INSTANTIATE_DISBURSER_REPLY_ID => execute::instantiate_reply(deps, env, msg),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_json_binary(&query::config(deps)?),
+        QueryMsg::Instance { addr } => to_json_binary(&query::instance(deps, addr)?),
+        QueryMsg::Instances {
+            filter,
+            start_after,
+            limit,
+        } => to_json_binary(&query::list_instances(deps, filter, start_after, limit)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{DisburserTemplate, InstanceFilter, InstancesResponse};
+    use crate::state::Instance;
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env, MockApi, MockQuerier};
+    use cosmwasm_std::{
+        coin, from_json, Addr, CosmosMsg, MemoryStorage, MsgResponse, OwnedDeps, SubMsgResponse,
+        SubMsgResult, Uint128, WasmMsg,
+    };
+    use cw_utils::Expiration;
+    use drip_disburser_interface::msg::{
+        DurationBounds, DurationLimit, InstantiateMsg as DisburserInstantiateMsg, SettlementAsset,
+    };
+
+    fn bounds(seconds: u64) -> DurationBounds {
+        DurationBounds {
+            default: DurationLimit { blocks: 0, seconds },
+            max: None,
+            min: None,
+        }
+    }
+
+    fn template(recipient: &Addr) -> DisburserTemplate {
+        DisburserTemplate {
+            settlement_asset: SettlementAsset::Native { denom: "SETTLE".to_string() },
+            settlement_asset_limit: Uint128::new(1_000),
+            quote_asset_limit: coin(1_000, "QUOTE"),
+            admin: None,
+            refund_addr: None,
+            recipient_addr: recipient.to_string(),
+            recipients: None,
+            oracle_addr: None,
+            payment_initiator_addrs: vec![],
+            funding_expiration: Expiration::Never {},
+            payout_duration_bounds: None,
+            price_band: None,
+            max_price_age: None,
+            cached_price_age: None,
+            quote_decimals: Some(6),
+            settlement_decimals: Some(6),
+            ibc_transfer_timeout: None,
+            keeper_bounty: None,
+            retry_policy: None,
+        }
+    }
+
+    /// Creates a disburser as `funder` and feeds the instantiate reply back as `addr`.
+    fn create(
+        deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+        funder: &Addr,
+        template: DisburserTemplate,
+        addr: &Addr,
+    ) -> CosmosMsg {
+        let resp = execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(funder, &[]),
+            ExecuteMsg::CreateDisburser { template: Box::new(template), label: "grant".to_string() },
+        )
+        .unwrap();
+        let sub = resp.messages[0].clone();
+
+        // MsgInstantiateContractResponse { address }
+        let mut value = vec![0x0a, addr.as_str().len() as u8];
+        value.extend_from_slice(addr.as_bytes());
+        #[allow(deprecated)]
+        let result = SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+            msg_responses: vec![MsgResponse {
+                type_url: "/cosmwasm.wasm.v1.MsgInstantiateContractResponse".to_string(),
+                value: value.into(),
+            }],
+        });
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply { id: sub.id, payload: sub.payload, gas_used: 0, result },
+        )
+        .unwrap();
+        sub.msg
+    }
+
+    fn list(
+        deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+        filter: Option<InstanceFilter>,
+        start_after: Option<String>,
+    ) -> Vec<Instance> {
+        let resp: InstancesResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::Instances { filter, start_after, limit: Some(1) }).unwrap(),
+        )
+        .unwrap();
+        resp.instances
+    }
+
+    #[test]
+    fn creates_and_indexes_disbursers() {
+        let mut deps = mock_dependencies();
+        let admin = deps.api.addr_make("admin");
+        let oracle = deps.api.addr_make("oracle");
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let recipient = deps.api.addr_make("recipient");
+        let first = deps.api.addr_make("disburser1");
+        let second = deps.api.addr_make("disburser2");
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            InstantiateMsg {
+                admin: None,
+                disburser_code_id: 7,
+                oracle_addr: oracle.to_string(),
+                payout_duration_bounds: bounds(60),
+            },
+        )
+        .unwrap();
+
+        // factory defaults are inherited and the funder becomes the admin
+        let msg = create(&mut deps, &alice, template(&recipient), &first);
+        let CosmosMsg::Wasm(WasmMsg::Instantiate { admin: wasm_admin, code_id, msg, .. }) = msg else {
+            panic!("expected an instantiate message");
+        };
+        assert_eq!(code_id, 7);
+        assert_eq!(wasm_admin, Some(admin.to_string()));
+        let msg: DisburserInstantiateMsg = from_json(msg).unwrap();
+        assert_eq!(msg.oracle_addr, oracle.to_string());
+        assert_eq!(msg.payout_duration_bounds, bounds(60));
+        assert_eq!(msg.admin, Some(alice.to_string()));
+
+        let custom = DisburserTemplate {
+            admin: Some(bob.to_string()),
+            payout_duration_bounds: Some(bounds(120)),
+            ..template(&recipient)
+        };
+        create(&mut deps, &alice, custom, &second);
+
+        let instance: Instance = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::Instance { addr: first.to_string() }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(instance.admin, alice);
+        assert_eq!(instance.funder, alice);
+        assert_eq!(instance.code_id, 7);
+
+        let by_admin = list(&deps, Some(InstanceFilter::Admin(bob.to_string())), None);
+        assert_eq!(by_admin.len(), 1);
+        assert_eq!(by_admin[0].addr, second);
+
+        // paginated by address within an index
+        let funded = list(&deps, Some(InstanceFilter::Funder(alice.to_string())), None);
+        let next = list(
+            &deps,
+            Some(InstanceFilter::Funder(alice.to_string())),
+            Some(funded[0].addr.to_string()),
+        );
+        assert_eq!(next.len(), 1);
+        assert_ne!(next[0].addr, funded[0].addr);
+        let last = list(&deps, Some(InstanceFilter::Funder(alice.to_string())), Some(next[0].addr.to_string()));
+        assert!(last.is_empty());
+        assert_eq!(list(&deps, None, Some(next[0].addr.to_string())).len(), 0);
+        assert_eq!(list(&deps, Some(InstanceFilter::Recipient(recipient.to_string())), None).len(), 1);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&alice, &[]),
+            ExecuteMsg::UpdateConfig {
+                admin: None,
+                disburser_code_id: Some(8),
+                oracle_addr: None,
+                payout_duration_bounds: None,
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn create_disburser_forwards_funds_to_the_funder() {
+        let mut deps = mock_dependencies();
+        let admin = deps.api.addr_make("admin");
+        let alice = deps.api.addr_make("alice");
+        let recipient = deps.api.addr_make("recipient");
+        let oracle = deps.api.addr_make("oracle");
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            InstantiateMsg {
+                admin: None,
+                disburser_code_id: 7,
+                oracle_addr: oracle.to_string(),
+                payout_duration_bounds: bounds(60),
+            },
+        )
+        .unwrap();
+
+        let resp = execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&alice, &[coin(100, "SETTLE")]),
+            ExecuteMsg::CreateDisburser { template: Box::new(template(&recipient)), label: "grant".to_string() },
+        )
+        .unwrap();
+        let CosmosMsg::Wasm(WasmMsg::Instantiate { msg, funds, .. }) = &resp.messages[0].msg else {
+            panic!("expected an instantiate message");
+        };
+        assert_eq!(funds, &vec![coin(100, "SETTLE")]);
+        let msg: DisburserInstantiateMsg = from_json(msg).unwrap();
+        assert_eq!(msg.funder, Some(alice.to_string()));
+    }
+
+    #[test]
+    fn admin_and_funder_indexes_page_independently() {
+        let mut deps = mock_dependencies();
+        let admin = deps.api.addr_make("admin");
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let recipient = deps.api.addr_make("recipient");
+        let oracle = deps.api.addr_make("oracle");
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            InstantiateMsg {
+                admin: None,
+                disburser_code_id: 7,
+                oracle_addr: oracle.to_string(),
+                payout_duration_bounds: bounds(60),
+            },
+        )
+        .unwrap();
+
+        // alice creates three disbursers, two of them administered by bob; bob creates one
+        let mut by_bob = vec![];
+        for (i, (funder, admin)) in [(&alice, None), (&alice, Some(&bob)), (&alice, Some(&bob)), (&bob, None)]
+            .into_iter()
+            .enumerate()
+        {
+            let addr = deps.api.addr_make(&format!("disburser{i}"));
+            let template = DisburserTemplate { admin: admin.map(|a| a.to_string()), ..template(&recipient) };
+            create(&mut deps, funder, template, &addr);
+            if admin.is_some() || *funder == bob {
+                by_bob.push(addr);
+            }
+        }
+        by_bob.sort();
+
+        let walk = |filter: InstanceFilter| {
+            let mut seen: Vec<Addr> = vec![];
+            loop {
+                let page = list(&deps, Some(filter.clone()), seen.last().map(|a| a.to_string()));
+                if page.is_empty() {
+                    return seen;
+                }
+                seen.extend(page.into_iter().map(|i| i.addr));
+            }
+        };
+
+        assert_eq!(walk(InstanceFilter::Admin(bob.to_string())), by_bob);
+        assert_eq!(walk(InstanceFilter::Funder(alice.to_string())).len(), 3);
+        assert_eq!(walk(InstanceFilter::Funder(bob.to_string())).len(), 1);
+        assert_eq!(walk(InstanceFilter::Admin(alice.to_string())).len(), 1);
+    }
+}
@@ -0,0 +1,138 @@
+use cosmwasm_std::{
+    ensure, from_json, to_json_binary, DepsMut, Env, MessageInfo, Reply, Response, StdError, SubMsg,
+    WasmMsg,
+};
+use cw_utils::parse_instantiate_response_data;
+use drip_disburser_interface::msg::{DurationBounds, InstantiateMsg as DisburserInstantiateMsg};
+
+use crate::error::ContractError;
+use crate::msg::DisburserTemplate;
+use crate::state::{instances, Instance, PendingInstance, CONFIG};
+
+pub const INSTANTIATE_DISBURSER_REPLY_ID: u64 = 1;
+
+/// Instantiates a disburser from `template`, filling in the factory defaults.
+/// The instance is registered once the reply reports its address.
+pub fn create_disburser(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    template: Box<DisburserTemplate>,
+    label: String,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+
+    let admin = match &template.admin {
+        Some(a) => deps.api.addr_validate(a)?,
+        None => info.sender.clone(),
+    };
+    let recipient = deps.api.addr_validate(&template.recipient_addr)?;
+
+    let msg = DisburserInstantiateMsg {
+        settlement_asset: template.settlement_asset,
+        settlement_asset_limit: template.settlement_asset_limit,
+        quote_asset_limit: template.quote_asset_limit,
+        admin: Some(admin.to_string()),
+        refund_addr: template.refund_addr,
+        recipient_addr: template.recipient_addr,
+        recipients: template.recipients,
+        oracle_addr: template
+            .oracle_addr
+            .unwrap_or_else(|| cfg.oracle_addr.to_string()),
+        payment_initiator_addrs: template.payment_initiator_addrs,
+        funding_expiration: template.funding_expiration,
+        payout_duration_bounds: template
+            .payout_duration_bounds
+            .unwrap_or(cfg.payout_duration_bounds),
+        price_band: template.price_band,
+        max_price_age: template.max_price_age,
+        cached_price_age: template.cached_price_age,
+        quote_decimals: template.quote_decimals,
+        settlement_decimals: template.settlement_decimals,
+        ibc_transfer_timeout: template.ibc_transfer_timeout,
+        keeper_bounty: template.keeper_bounty,
+        retry_policy: template.retry_policy,
+        funder: Some(info.sender.to_string()),
+    };
+    let pending = PendingInstance {
+        code_id: cfg.disburser_code_id,
+        label: label.clone(),
+        admin: admin.clone(),
+        recipient,
+        funder: info.sender.clone(),
+    };
+    let instantiate = WasmMsg::Instantiate {
+        admin: Some(cfg.admin.to_string()),
+        code_id: cfg.disburser_code_id,
+        msg: to_json_binary(&msg)?,
+        funds: info.funds.clone(),
+        label,
+    };
+
+    Ok(Response::new()
+        .add_submessage(
+            SubMsg::reply_on_success(instantiate, INSTANTIATE_DISBURSER_REPLY_ID)
+                .with_payload(to_json_binary(&pending)?),
+        )
+        .add_attribute("action", "create_disburser")
+        .add_attribute("code_id", cfg.disburser_code_id.to_string())
+        .add_attribute("admin", admin)
+        .add_attribute("funder", info.sender)
+        .add_attribute("factory", env.contract.address))
+}
+
+/// Registers the disburser instantiated by `create_disburser`.
+pub fn instantiate_reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let pending: PendingInstance = from_json(&msg.payload)?;
+    let response = msg.result.into_result().map_err(StdError::generic_err)?;
+    let data = response
+        .msg_responses
+        .first()
+        .ok_or_else(|| StdError::generic_err("missing instantiate response"))?;
+    let addr = deps
+        .api
+        .addr_validate(&parse_instantiate_response_data(&data.value)?.contract_address)?;
+
+    let instance = Instance {
+        addr: addr.clone(),
+        code_id: pending.code_id,
+        label: pending.label,
+        admin: pending.admin,
+        recipient: pending.recipient,
+        funder: pending.funder,
+        created_at: env.block.time,
+    };
+    instances().save(deps.storage, &addr, &instance)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_disburser")
+        .add_attribute("disburser", addr))
+}
+
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin: Option<String>,
+    disburser_code_id: Option<u64>,
+    oracle_addr: Option<String>,
+    payout_duration_bounds: Option<DurationBounds>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    ensure!(info.sender == cfg.admin, ContractError::Unauthorized {});
+
+    if let Some(admin) = admin {
+        cfg.admin = deps.api.addr_validate(&admin)?;
+    }
+    if let Some(code_id) = disburser_code_id {
+        cfg.disburser_code_id = code_id;
+    }
+    if let Some(oracle) = oracle_addr {
+        cfg.oracle_addr = deps.api.addr_validate(&oracle)?;
+    }
+    if let Some(bounds) = payout_duration_bounds {
+        cfg.payout_duration_bounds = bounds;
+    }
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new().add_attribute("action", "update_config"))
+}
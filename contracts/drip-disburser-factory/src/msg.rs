@@ -0,0 +1,88 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Coin, Uint128};
+use cw_utils::Expiration;
+use drip_disburser_interface::msg::{
+    DurationBounds, KeeperBounty, PriceBand, RecipientWeight, RetryPolicy, SettlementAsset,
+};
+
+use crate::state::{Config, Instance};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub admin: Option<String>,
+    pub disburser_code_id: u64,
+    /// Oracle of new disbursers unless the template sets one
+    pub oracle_addr: String,
+    /// Payout duration bounds of new disbursers unless the template sets them
+    pub payout_duration_bounds: DurationBounds,
+}
+
+/// Disburser `InstantiateMsg` with the oracle and duration bounds inherited from the factory
+/// when left empty. The disburser admin defaults to the funder.
+#[cw_serde]
+pub struct DisburserTemplate {
+    pub settlement_asset: SettlementAsset,
+    pub settlement_asset_limit: Uint128,
+    pub quote_asset_limit: Coin,
+    pub admin: Option<String>,
+    pub refund_addr: Option<String>,
+    pub recipient_addr: String,
+    pub recipients: Option<Vec<RecipientWeight>>,
+    pub oracle_addr: Option<String>,
+    pub payment_initiator_addrs: Vec<String>,
+    pub funding_expiration: Expiration,
+    pub payout_duration_bounds: Option<DurationBounds>,
+    pub price_band: Option<PriceBand>,
+    pub max_price_age: Option<u64>,
+    pub cached_price_age: Option<u64>,
+    pub quote_decimals: Option<u32>,
+    pub settlement_decimals: Option<u32>,
+    pub ibc_transfer_timeout: Option<u64>,
+    pub keeper_bounty: Option<KeeperBounty>,
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Instantiates a disburser from the template; the sender is recorded as its funder.
+    /// Settlement tokens sent along are forwarded to the disburser and credited to the sender.
+    CreateDisburser {
+        template: Box<DisburserTemplate>,
+        label: String,
+    },
+    UpdateConfig {
+        admin: Option<String>,
+        disburser_code_id: Option<u64>,
+        oracle_addr: Option<String>,
+        payout_duration_bounds: Option<DurationBounds>,
+    },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(Config)]
+    Config {},
+    #[returns(Instance)]
+    Instance { addr: String },
+    /// Instances in ascending address order, optionally only those of one admin,
+    /// recipient or funder
+    #[returns(InstancesResponse)]
+    Instances {
+        filter: Option<InstanceFilter>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub enum InstanceFilter {
+    Admin(String),
+    Recipient(String),
+    Funder(String),
+}
+
+#[cw_serde]
+pub struct InstancesResponse {
+    pub instances: Vec<Instance>,
+}
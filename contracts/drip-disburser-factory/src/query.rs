@@ -0,0 +1,54 @@
+use cosmwasm_std::{Addr, Deps, Order, StdResult};
+use cw_storage_plus::Bound;
+
+use crate::msg::{InstanceFilter, InstancesResponse};
+use crate::state::{instances, Config, Instance, CONFIG};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn config(deps: Deps) -> StdResult<Config> {
+    CONFIG.load(deps.storage)
+}
+
+pub fn instance(deps: Deps, addr: String) -> StdResult<Instance> {
+    let addr = deps.api.addr_validate(&addr)?;
+    instances().load(deps.storage, &addr)
+}
+
+pub fn list_instances(
+    deps: Deps,
+    filter: Option<InstanceFilter>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<InstancesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .map(|a| deps.api.addr_validate(&a))
+        .transpose()?;
+
+    let map = instances();
+    let instances = match filter {
+        None => map
+            .range(deps.storage, start_after.as_ref().map(Bound::exclusive), None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, i)| i))
+            .collect::<StdResult<Vec<_>>>()?,
+        Some(filter) => {
+            let (index, key) = match filter {
+                InstanceFilter::Admin(a) => (&map.idx.admin, a),
+                InstanceFilter::Recipient(a) => (&map.idx.recipient, a),
+                InstanceFilter::Funder(a) => (&map.idx.funder, a),
+            };
+            let key: Addr = deps.api.addr_validate(&key)?;
+            index
+                .prefix(key)
+                .range(deps.storage, start_after.map(Bound::exclusive), None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, i)| i))
+                .collect::<StdResult<Vec<_>>>()?
+        }
+    };
+
+    Ok(InstancesResponse { instances })
+}
@@ -0,0 +1,61 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Timestamp};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use drip_disburser_interface::msg::DurationBounds;
+
+#[cw_serde]
+pub struct Config {
+    pub admin: Addr,
+    pub disburser_code_id: u64,
+    pub oracle_addr: Addr,
+    pub payout_duration_bounds: DurationBounds,
+}
+
+/// Disburser created by the factory
+#[cw_serde]
+pub struct Instance {
+    pub addr: Addr,
+    pub code_id: u64,
+    pub label: String,
+    pub admin: Addr,
+    /// Sole recipient, or the one receiving the rounding dust of weighted payouts
+    pub recipient: Addr,
+    /// Account that created the grant, credited with the funds sent along
+    pub funder: Addr,
+    pub created_at: Timestamp,
+}
+
+pub struct InstanceIndexes<'a> {
+    pub admin: MultiIndex<'a, Addr, Instance, Addr>,
+    pub recipient: MultiIndex<'a, Addr, Instance, Addr>,
+    pub funder: MultiIndex<'a, Addr, Instance, Addr>,
+}
+
+impl IndexList<Instance> for InstanceIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Instance>> + '_> {
+        let v: Vec<&dyn Index<Instance>> = vec![&self.admin, &self.recipient, &self.funder];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn instances<'a>() -> IndexedMap<&'a Addr, Instance, InstanceIndexes<'a>> {
+    let indexes = InstanceIndexes {
+        admin: MultiIndex::new(|_, i| i.admin.clone(), "instances", "instances__admin"),
+        recipient: MultiIndex::new(|_, i| i.recipient.clone(), "instances", "instances__recipient"),
+        funder: MultiIndex::new(|_, i| i.funder.clone(), "instances", "instances__funder"),
+    };
+    IndexedMap::new("instances", indexes)
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+/// Instance details carried in the instantiate submessage payload until the reply
+/// reports the new address
+#[cw_serde]
+pub struct PendingInstance {
+    pub code_id: u64,
+    pub label: String,
+    pub admin: Addr,
+    pub recipient: Addr,
+    pub funder: Addr,
+}
@@ -0,0 +1,108 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coins, to_json_binary, Addr, Api, BankMsg, CosmosMsg, QuerierWrapper, StdError, StdResult,
+    Uint128, WasmMsg,
+};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, TokenInfoResponse};
+use drip_disburser_interface::msg::SettlementAsset;
+
+/// Validated settlement asset
+#[cw_serde]
+pub enum Asset {
+    Native { denom: String },
+    Cw20 { contract_addr: Addr },
+}
+
+impl Asset {
+    pub fn from_msg(api: &dyn Api, asset: SettlementAsset) -> StdResult<Self> {
+        Ok(match asset {
+            SettlementAsset::Native { denom } => Asset::Native { denom },
+            SettlementAsset::Cw20 { contract_addr } => Asset::Cw20 {
+                contract_addr: api.addr_validate(&contract_addr)?,
+            },
+        })
+    }
+
+    /// Identifier passed to the oracle as the base asset: the denom or the CW20 address
+    pub fn id(&self) -> String {
+        match self {
+            Asset::Native { denom } => denom.clone(),
+            Asset::Cw20 { contract_addr } => contract_addr.to_string(),
+        }
+    }
+
+    pub fn native_denom(&self) -> Option<&str> {
+        match self {
+            Asset::Native { denom } => Some(denom),
+            Asset::Cw20 { .. } => None,
+        }
+    }
+
+    pub fn query_balance(&self, querier: &QuerierWrapper, addr: &Addr) -> StdResult<Uint128> {
+        match self {
+            Asset::Native { denom } => Ok(querier.query_balance(addr, denom)?.amount),
+            Asset::Cw20 { contract_addr } => {
+                let resp: BalanceResponse = querier.query_wasm_smart(
+                    contract_addr,
+                    &Cw20QueryMsg::Balance {
+                        address: addr.to_string(),
+                    },
+                )?;
+                Ok(resp.balance)
+            }
+        }
+    }
+
+    /// Decimal exponent of the token, from the CW20 token info for a CW20
+    pub fn query_decimals(&self, querier: &QuerierWrapper) -> StdResult<u32> {
+        match self {
+            Asset::Native { denom } => query_native_decimals(querier, denom),
+            Asset::Cw20 { contract_addr } => {
+                let info: TokenInfoResponse =
+                    querier.query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
+                Ok(info.decimals.into())
+            }
+        }
+    }
+
+    pub fn transfer_msg(&self, to: impl Into<String>, amount: Uint128) -> StdResult<CosmosMsg> {
+        Ok(match self {
+            Asset::Native { denom } => BankMsg::Send {
+                to_address: to.into(),
+                amount: coins(amount.u128(), denom),
+            }
+            .into(),
+            Asset::Cw20 { contract_addr } => WasmMsg::Execute {
+                contract_addr: contract_addr.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: to.into(),
+                    amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        })
+    }
+}
+
+/// Exponent of the display unit in the bank denom metadata of `denom`
+pub fn query_native_decimals(querier: &QuerierWrapper, denom: &str) -> StdResult<u32> {
+    let metadata = querier.query_denom_metadata(denom)?;
+    metadata
+        .denom_units
+        .iter()
+        .find(|unit| unit.denom == metadata.display)
+        .map(|unit| unit.exponent)
+        .ok_or_else(|| StdError::generic_err(format!("no display unit in the metadata of {denom}")))
+}
+
+impl From<Asset> for SettlementAsset {
+    fn from(asset: Asset) -> Self {
+        match asset {
+            Asset::Native { denom } => SettlementAsset::Native { denom },
+            Asset::Cw20 { contract_addr } => SettlementAsset::Cw20 {
+                contract_addr: contract_addr.to_string(),
+            },
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use cosmwasm_std::{ensure, Addr, BlockInfo, Storage};
+
+use crate::error::ContractError;
+use crate::state::{Config, Initiator, INITIATORS};
+
+pub fn ensure_admin(cfg: &Config, sender: &Addr) -> Result<(), ContractError> {
+    ensure!(*sender == cfg.admin, ContractError::Unauthorized {});
+    Ok(())
+}
+
+/// Loads the payment initiator entry of `sender`, rejecting unknown or expired initiators.
+pub fn load_initiator(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    sender: &Addr,
+) -> Result<Initiator, ContractError> {
+    let initiator = INITIATORS
+        .may_load(storage, sender)?
+        .ok_or(ContractError::Unauthorized {})?;
+    ensure!(
+        !initiator.expires_at.is_some_and(|e| e.is_expired(block)),
+        ContractError::InitiatorExpired {}
+    );
+    Ok(initiator)
+}
@@ -0,0 +1,16 @@
+use cosmwasm_std::ensure;
+pub use drip_keeper_bounty::keeper_reward;
+use drip_disburser_interface::msg::KeeperBounty;
+
+use crate::asset::Asset;
+use crate::error::ContractError;
+
+/// The pool is the contract's balance of the reward denom, so it must not be the
+/// settlement denom or rewards would eat into the funders' deposits.
+pub fn validate_keeper_bounty(asset: &Asset, bounty: &KeeperBounty) -> Result<(), ContractError> {
+    ensure!(
+        bounty.is_valid() && asset.native_denom() != Some(bounty.reward.denom.as_str()),
+        ContractError::InvalidKeeperBounty {}
+    );
+    Ok(())
+}
@@ -0,0 +1,302 @@
+use cosmwasm_std::{
+    ensure, BlockInfo, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, StdError, Storage,
+    SubMsg, Uint128,
+};
+use cw_utils::Scheduled;
+
+use crate::contract::price_request_msg;
+use crate::error::ContractError;
+use drip_disburser_interface::msg::{
+    Duration, LifecycleStatus, PayoutRecord, PayoutShare, PayoutStatus, PriceBand,
+};
+
+use crate::helpers::{price_in_band, quote_to_settlement, settlement_to_quote};
+use crate::ibc;
+
+use crate::state::{
+    payouts, release_allowance, take_pending, BudgetUsage, Config, IbcTransfer, PendingPayout,
+    BUDGET_USAGE, CONFIG, PENDING_PAYOUTS, STATUS, TOTAL_WEIGHT_BPS,
+};
+
+/// Settles the pending payout `request_id` once the oracle delivers its price.
+///
+/// `price` is the price of one whole settlement token expressed in whole quote tokens;
+/// the settlement amount is `amount_in_quote / price` scaled by the decimals of both
+/// assets, rounded down and capped by the contract balance and the remaining settlement budget, then split between the
+/// weighted recipients. Shares of remote recipients leave over ICS-20.
+/// An expired pending payout, or one whose price is zero, stale or outside the request or
+/// global price band, or whose settlement math overflows, is closed without moving any funds.
+/// A price is stale when it was observed before the request, less `cached_price_age`, or
+/// is older than `max_price_age`.
+///
+/// A price arriving while the disburser is paused cancels the payout instead of failing:
+/// the oracle drops failed callbacks, so an error would leave the reservation behind.
+pub fn on_payout_response(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    price: Decimal,
+    price_timestamp: u64,
+    request_id: u64,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure!(info.sender == cfg.price_feeder_addr, ContractError::Unauthorized {});
+
+    let pending = take_pending(deps.storage, request_id)?;
+
+    let resp = Response::new()
+        .add_attribute("action", "on_payout_response")
+        .add_attribute("request_id", request_id.to_string());
+
+    let status = STATUS.load(deps.storage)?;
+    if status != LifecycleStatus::Active {
+        release_allowance(deps.storage, &pending.requester, pending.amount_in_quote)?;
+        let record = PayoutRecord {
+            price: Some(price),
+            reason: Some(format!("disburser {}", format!("{status:?}").to_lowercase())),
+            ..pending.record(&env.block, PayoutStatus::Cancelled)
+        };
+        payouts().save(deps.storage, record.id, &record)?;
+        return Ok(resp.add_attribute("status", "cancelled"));
+    }
+
+    if pending.expires_at.is_expired(&env.block) {
+        release_allowance(deps.storage, &pending.requester, pending.amount_in_quote)?;
+        let record = pending.record(&env.block, PayoutStatus::TimedOut);
+        payouts().save(deps.storage, record.id, &record)?;
+        return Ok(resp.add_attribute("status", "expired"));
+    }
+
+    let price_age = env.block.time.seconds().saturating_sub(price_timestamp);
+    let observable_from = pending
+        .created_at
+        .seconds()
+        .saturating_sub(cfg.cached_price_age.unwrap_or_default());
+    let stale = price_timestamp < observable_from
+        || cfg.max_price_age.is_some_and(|max_age| price_age > max_age);
+    let bands: Vec<&PriceBand> = cfg.price_band.iter().chain([&pending.price_band]).collect();
+    let failure = if price.is_zero() {
+        Some("zero price")
+    } else if stale {
+        Some("stale price")
+    } else if !price_in_band(price, &bands) {
+        Some("price outside band")
+    } else {
+        None
+    };
+    if let Some(reason) = failure {
+        return fail_payout(deps.storage, &env.block, resp, pending, price, reason);
+    }
+
+    let balance = cfg
+        .settlement_asset
+        .query_balance(&deps.querier, &env.contract.address)?;
+    let mut usage = BUDGET_USAGE.load(deps.storage)?;
+    let Ok((amount, quote_paid)) =
+        settlement_amounts(&cfg, &usage, balance, pending.amount_in_quote, price)
+    else {
+        return fail_payout(deps.storage, &env.block, resp, pending, price, "settlement math overflow");
+    };
+
+    usage.quote += quote_paid;
+    usage.settlement += amount;
+    BUDGET_USAGE.save(deps.storage, &usage)?;
+    release_allowance(deps.storage, &pending.requester, pending.amount_in_quote - quote_paid)?;
+
+    let shares = split_payout(&cfg, amount);
+    let record = PayoutRecord {
+        price: Some(price),
+        settlement_amount: amount,
+        recipients: shares.clone(),
+        ..pending.record(&env.block, PayoutStatus::Settled)
+    };
+    payouts().save(deps.storage, record.id, &record)?;
+
+    let mut transfers = Vec::with_capacity(shares.len());
+    for share in shares {
+        let msg = match &share.channel_id {
+            Some(channel_id) => ibc::transfer_submsg(
+                &env,
+                &cfg,
+                IbcTransfer {
+                    payout_id: record.id,
+                    requester: pending.requester.clone(),
+                    channel_id: channel_id.clone(),
+                    amount: share.amount,
+                    quote_amount: quote_paid.multiply_ratio(share.amount, amount),
+                },
+                share.addr,
+            )?,
+            None => SubMsg::new(cfg.settlement_asset.transfer_msg(share.addr, share.amount)?),
+        };
+        transfers.push(msg);
+    }
+
+    Ok(resp
+        .add_submessages(transfers)
+        .add_attribute("status", "settled")
+        .add_attribute("price", price.to_string())
+        .add_attribute("settlement_amount", amount.to_string())
+        .add_attribute("quote_paid", quote_paid.to_string()))
+}
+
+/// Closes `pending` as failed at `price`, releasing the initiator allowance.
+fn fail_payout(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    resp: Response,
+    pending: PendingPayout,
+    price: Decimal,
+    reason: &str,
+) -> Result<Response, ContractError> {
+    release_allowance(storage, &pending.requester, pending.amount_in_quote)?;
+    let record = PayoutRecord {
+        price: Some(price),
+        reason: Some(reason.to_string()),
+        ..pending.record(block, PayoutStatus::Failed)
+    };
+    payouts().save(storage, record.id, &record)?;
+    Ok(resp
+        .add_attribute("status", "failed")
+        .add_attribute("reason", reason)
+        .add_attribute("price", price.to_string()))
+}
+
+/// Handles the oracle giving up on the price request of the pending payout `request_id`.
+///
+/// Under a retry policy the payout stays pending until `max_attempts` requests were sent:
+/// the next one goes out right away without backoff, otherwise through `Crank` once the
+/// backoff has passed. Without retries left, or once the payout itself expired, it is
+/// closed as timed out and its reservation and allowance are released, leaving the
+/// initiator to request again.
+pub fn on_payout_timeout(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    request_id: u64,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure!(info.sender == cfg.price_feeder_addr, ContractError::Unauthorized {});
+
+    let mut pending = PENDING_PAYOUTS
+        .may_load(deps.storage, request_id)?
+        .ok_or(ContractError::UnknownPayoutRequest { request_id })?;
+
+    let resp = Response::new()
+        .add_attribute("action", "on_payout_timeout")
+        .add_attribute("request_id", request_id.to_string())
+        .add_attribute("attempts", pending.attempts.to_string());
+
+    let retry_at = cfg
+        .retry_policy
+        .as_ref()
+        .filter(|policy| pending.attempts < policy.max_attempts)
+        .map(|policy| backoff_until(&env.block, &policy.backoff));
+    let Some(retry_at) = retry_at.filter(|_| !pending.expires_at.is_expired(&env.block)) else {
+        close_timed_out(deps.storage, &env.block, request_id)?;
+        return Ok(resp.add_attribute("status", "timed_out"));
+    };
+
+    if retry_at.is_triggered(&env.block) {
+        let msg = retry_price_request(deps.storage, &cfg, pending)?;
+        return Ok(resp.add_message(msg).add_attribute("status", "retried"));
+    }
+    pending.retry_at = Some(retry_at);
+    PENDING_PAYOUTS.save(deps.storage, request_id, &pending)?;
+
+    Ok(resp
+        .add_attribute("status", "retry_scheduled")
+        .add_attribute("retry_at", retry_at.to_string()))
+}
+
+/// Sends the price request of `pending` again, counting the attempt.
+pub(crate) fn retry_price_request(
+    storage: &mut dyn Storage,
+    cfg: &Config,
+    mut pending: PendingPayout,
+) -> Result<CosmosMsg, ContractError> {
+    pending.attempts += 1;
+    pending.retry_at = None;
+    PENDING_PAYOUTS.save(storage, pending.seq, &pending)?;
+    Ok(price_request_msg(cfg, &pending)?)
+}
+
+/// Closes the pending payout `seq` whose price never arrived.
+pub(crate) fn close_timed_out(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    seq: u64,
+) -> Result<(), ContractError> {
+    let pending = take_pending(storage, seq)?;
+    release_allowance(storage, &pending.requester, pending.amount_in_quote)?;
+    let record = PayoutRecord {
+        reason: Some(format!("price request timed out after {} attempts", pending.attempts)),
+        ..pending.record(block, PayoutStatus::TimedOut)
+    };
+    payouts().save(storage, record.id, &record)?;
+    Ok(())
+}
+
+fn backoff_until(block: &BlockInfo, backoff: &Duration) -> Scheduled {
+    match backoff {
+        Duration::Blocks(n) => Scheduled::AtHeight(block.height + n),
+        Duration::Seconds(n) => Scheduled::AtTime(block.time.plus_seconds(*n)),
+    }
+}
+
+/// Settlement amount paid for `amount_in_quote` at `price` and the quote value it delivers.
+///
+/// The settlement amount is rounded down and capped by `balance` and the remaining
+/// settlement budget; the quote value is rounded up, in the contract's favour against
+/// the budget, and is less than requested when the amount was trimmed.
+pub(crate) fn settlement_amounts(
+    cfg: &Config,
+    usage: &BudgetUsage,
+    balance: Uint128,
+    amount_in_quote: Uint128,
+    price: Decimal,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let cap = balance.min(usage.settlement_remaining(cfg));
+    let amount = quote_to_settlement(cfg, amount_in_quote, price)?
+        .min(cap.into())
+        .try_into()
+        .map_err(StdError::from)?;
+    let quote_paid = settlement_to_quote(cfg, amount, price)?
+        .min(amount_in_quote.into())
+        .min(usage.quote_remaining(cfg).into())
+        .try_into()
+        .map_err(StdError::from)?;
+    Ok((amount, quote_paid))
+}
+
+/// Splits `amount` by recipient weight, rounding down; the dust goes to `recipient_addr`.
+pub(crate) fn split_payout(cfg: &Config, amount: Uint128) -> Vec<PayoutShare> {
+    let mut shares: Vec<PayoutShare> = cfg
+        .recipients
+        .iter()
+        .map(|r| PayoutShare {
+            addr: r.addr.clone(),
+            amount: amount.multiply_ratio(r.weight_bps, TOTAL_WEIGHT_BPS),
+            channel_id: r.channel_id.clone(),
+        })
+        .collect();
+
+    let distributed: Uint128 = shares.iter().map(|s| s.amount).sum();
+    let dust = amount - distributed;
+    if !dust.is_zero() {
+        let dust_share = shares
+            .iter_mut()
+            .find(|s| s.channel_id.is_none() && s.addr == cfg.recipient_addr.as_str());
+        match dust_share {
+            Some(share) => share.amount += dust,
+            None => shares.push(PayoutShare {
+                addr: cfg.recipient_addr.to_string(),
+                amount: dust,
+                channel_id: None,
+            }),
+        }
+    }
+
+    shares.retain(|s| !s.amount.is_zero());
+    shares
+}
@@ -1,22 +1,35 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, ensure, Uint128, Addr, StdError, WasmMsg, CosmosMsg, to_json_binary};
+use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response, Storage, StdResult, ensure, Uint128, StdError, WasmMsg, CosmosMsg, to_json_binary};
 use cw2::set_contract_version;
-use cw_utils::Expiration;
-use drip_disburser_interface::msg::{Duration, DurationBounds, ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw_utils::{must_pay, Expiration};
+use drip_disburser_interface::msg::{Duration, DurationBounds, ExecuteMsg, InstantiateMsg, LifecycleStatus, PayoutStatus, PriceBand, QueryMsg};
 
+use crate::asset::{query_native_decimals, Asset};
+use crate::bounty::validate_keeper_bounty;
+use crate::callbacks;
 use crate::error::ContractError;
-use crate::state::{Config, PendingPayout, CONFIG, PENDING_PAYOUT};
+use crate::auth::load_initiator;
+use crate::execute;
+use crate::helpers::{validate_price_band, validate_recipients, validate_retry_policy};
+use crate::ibc;
+use crate::query;
+use crate::schedule;
+use crate::state::{
+    close_pending, ensure_active, BudgetUsage, Config, Initiator, PendingPayout, Recipient,
+    BUDGET_USAGE, CONFIG, DEFAULT_IBC_TRANSFER_TIMEOUT, INITIATORS, MAX_DECIMALS, MAX_PENDING_PAYOUTS, PAYOUT_SEQ, PENDING_PAYOUTS, STATUS, TOTAL_FUNDED,
+    TOTAL_WEIGHT_BPS,
+};
 use drip_price_oracle_interface::msg::ExecuteMsg as OracleExecuteMsg;
 
 // version info for migration info
-const CONTRACT_NAME: &str = "crates.io:drip-disburser";
-const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub(crate) const CONTRACT_NAME: &str = "crates.io:drip-disburser";
+pub(crate) const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
@@ -27,31 +40,93 @@ pub fn instantiate(
         Some(a) => deps.api.addr_validate(a)?,
         None => info.sender.clone(),
     };
+    let refund_addr = match &msg.refund_addr {
+        Some(a) => deps.api.addr_validate(a)?,
+        None => admin.clone(),
+    };
     let recipient = deps.api.addr_validate(&msg.recipient_addr)?;
+    let funder = match &msg.funder {
+        Some(a) => deps.api.addr_validate(a)?,
+        None => info.sender.clone(),
+    };
+    let settlement_asset = Asset::from_msg(deps.api, msg.settlement_asset)?;
+    let recipients = match msg.recipients {
+        Some(r) => validate_recipients(deps.api, &settlement_asset, r)?,
+        None => vec![Recipient {
+            addr: recipient.to_string(),
+            weight_bps: TOTAL_WEIGHT_BPS,
+            channel_id: None,
+        }],
+    };
     let oracle = deps.api.addr_validate(&msg.oracle_addr)?;
-    let initiators = msg
-        .payment_initiator_addrs
-        .into_iter()
-        .map(|s| deps.api.addr_validate(&s))
-        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(band) = &msg.price_band {
+        validate_price_band(band)?;
+    }
+    if let Some(bounty) = &msg.keeper_bounty {
+        validate_keeper_bounty(&settlement_asset, bounty)?;
+    }
+    if let Some(policy) = &msg.retry_policy {
+        validate_retry_policy(policy)?;
+    }
+    let quote_decimals = match msg.quote_decimals {
+        Some(d) => d,
+        None => query_native_decimals(&deps.querier, &msg.quote_asset_limit.denom)?,
+    };
+    let settlement_decimals = match msg.settlement_decimals {
+        Some(d) => d,
+        None => settlement_asset.query_decimals(&deps.querier)?,
+    };
+    for decimals in [quote_decimals, settlement_decimals] {
+        ensure!(decimals <= MAX_DECIMALS, ContractError::InvalidDecimals { decimals });
+    }
+
 
     // assemble and store config
     let cfg = Config {
+        settlement_asset,
         settlement_asset_limit: msg.settlement_asset_limit,
         quote_asset_limit: msg.quote_asset_limit,
         admin,
+        refund_addr,
         recipient_addr: recipient,
+        recipients,
         price_feeder_addr: oracle,
-        payment_initiator_addrs: initiators,
         funding_expiration: msg.funding_expiration,
         payout_duration_bounds: msg.payout_duration_bounds,
+        price_band: msg.price_band,
+        max_price_age: msg.max_price_age,
+        cached_price_age: msg.cached_price_age,
+        quote_decimals,
+        settlement_decimals,
+        ibc_transfer_timeout: msg.ibc_transfer_timeout.unwrap_or(DEFAULT_IBC_TRANSFER_TIMEOUT),
+        keeper_bounty: msg.keeper_bounty,
+        retry_policy: msg.retry_policy,
     };
     CONFIG.save(deps.storage, &cfg)?;
+    BUDGET_USAGE.save(deps.storage, &BudgetUsage::default())?;
+    STATUS.save(deps.storage, &LifecycleStatus::Active)?;
+    TOTAL_FUNDED.save(deps.storage, &Uint128::zero())?;
+    for addr in msg.payment_initiator_addrs {
+        let addr = deps.api.addr_validate(&addr)?;
+        INITIATORS.save(deps.storage, &addr, &Initiator::unlimited())?;
+    }
 
     // version for migrations
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    Ok(Response::new().add_attribute("action", "instantiate"))
+    let mut resp = Response::new().add_attribute("action", "instantiate");
+    if !info.funds.is_empty() {
+        let denom = cfg
+            .settlement_asset
+            .native_denom()
+            .ok_or(ContractError::InvalidSettlementAsset {})?;
+        let amount = must_pay(&info, denom)?;
+        execute::credit_funder(deps.branch(), &env, &cfg, funder.clone(), amount)?;
+        resp = resp
+            .add_attribute("funder", funder)
+            .add_attribute("amount", amount);
+    }
+    Ok(resp)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -62,89 +137,143 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
+        ExecuteMsg::Fund {} => execute::fund(deps, env, info),
+        ExecuteMsg::Receive(msg) => execute::receive(deps, env, info, msg),
         ExecuteMsg::RequestPayout {
             amount_in_quote,
             duration_limit,
-            replace_pending
-        } => execute_request_payout(deps, env, info, amount_in_quote, duration_limit, replace_pending),
+            replace_pending,
+            min_price,
+            max_price,
+        } => execute_request_payout(
+            deps,
+            env,
+            info,
+            amount_in_quote,
+            duration_limit,
+            replace_pending,
+            PriceBand { min: min_price, max: max_price },
+        ),
         ExecuteMsg::OnPayoutResponse {
             price,
-            // price_timestamp,
+            price_timestamp,
             request_id: request_seq
-        } => unimplemented!(),
-        ExecuteMsg::Terminate {} => unimplemented!(),
-        ExecuteMsg::CancelPendingPayout {
-            expected_seq
-        } => unimplemented!(),
-        ExecuteMsg::UpdateAdmin {
-            admin
-        } => unimplemented!(),
+        } => callbacks::on_payout_response(deps, env, info, price, price_timestamp, request_seq),
+        ExecuteMsg::OnPayoutTimeout { request_id } => {
+            callbacks::on_payout_timeout(deps, env, info, request_id)
+        }
+        ExecuteMsg::Terminate {} => execute::terminate(deps, env, info),
+        ExecuteMsg::Pause {} => execute::pause(deps, info),
+        ExecuteMsg::Resume {} => execute::resume(deps, info),
+        ExecuteMsg::Sweep {} => execute::sweep(deps, env, info),
+        ExecuteMsg::CancelPendingPayout { expected_seq } => {
+            execute::cancel_pending_payout(deps, env, info, expected_seq)
+        }
+        ExecuteMsg::UpdateAdmin { .. } => unimplemented!(),
+        ExecuteMsg::UpdateRecipients {
+            recipients,
+            dust_recipient,
+        } => execute::update_recipients(deps, info, recipients, dust_recipient),
         ExecuteMsg::AddPaymentInitiator {
-            addr
-        } => unimplemented!(),
-        ExecuteMsg::RemovePaymentInitiator {
-            addr
-        } => unimplemented!(),
-        ExecuteMsg::UpdatePriceFeeder {
-            addr
-        } => unimplemented!(),
-        ExecuteMsg::UpdateWithdrawalTtl {
-            ttl
-        } => unimplemented!(),
-        ExecuteMsg::OnPayoutTimeout {
-            request_id
-        } => unimplemented!(),
+            addr,
+            allowance,
+            expires_at,
+            max_per_request,
+        } => execute::add_payment_initiator(deps, info, addr, allowance, expires_at, max_per_request),
+        ExecuteMsg::RemovePaymentInitiator { addr } => {
+            execute::remove_payment_initiator(deps, info, addr)
+        }
+        ExecuteMsg::UpdatePriceFeeder { .. } => unimplemented!(),
+        ExecuteMsg::UpdatePriceBand { price_band } => {
+            execute::update_price_band(deps, info, price_band)
+        }
+        ExecuteMsg::UpdateCachedPriceAge { cached_price_age } => {
+            execute::update_cached_price_age(deps, info, cached_price_age)
+        }
+        ExecuteMsg::UpdateKeeperBounty { keeper_bounty } => {
+            execute::update_keeper_bounty(deps, info, keeper_bounty)
+        }
+        ExecuteMsg::UpdateRetryPolicy { retry_policy } => {
+            execute::update_retry_policy(deps, info, retry_policy)
+        }
+        ExecuteMsg::AddSchedule { schedule } => schedule::add_schedule(deps, info, schedule),
+        ExecuteMsg::RemoveSchedule { id } => schedule::remove_schedule(deps, info, id),
+        ExecuteMsg::Crank {} => schedule::crank(deps, env, info),
+        ExecuteMsg::UpdateWithdrawalTtl { .. } => unimplemented!(),
     }
 }
 
 fn execute_request_payout(
-    mut deps: DepsMut,
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
     amount_in_quote: Option<Uint128>,
     duration: Option<Duration>,
     replace_pending: Option<bool>,
+    price_band: PriceBand,
 ) -> Result<Response, ContractError> {
-    // TODO: validate caller is allowed to request payout (payment initiator)
-    // TODO: check contract lifecycle/state allows new payout requests (not terminated/paused)
+    ensure_active(deps.storage)?;
+    load_initiator(deps.storage, &env.block, &info.sender)?;
 
     if let Some(a) = amount_in_quote {
         ensure!(a > Uint128::zero(), ContractError::InvalidAmount {});
     }
+    validate_price_band(&price_band)?;
 
     let cfg = CONFIG.load(deps.storage)?;
+    ensure!(
+        !cfg.funding_expiration.is_expired(&env.block),
+        ContractError::FundingExpired {}
+    );
     let expires_at = normalize_duration_to_expiration(&env, &cfg.payout_duration_bounds, duration)
         .map_err(|e| ContractError::InvalidDuration { reason: e.to_string() })?;
 
-    let has_pending = PENDING_PAYOUT.may_load(deps.storage)?.is_some();
-    if has_pending && !replace_pending.unwrap_or(false) {
-        return Err(ContractError::PendingAlreadyExists {});
+    let mut resp = Response::new();
+    if replace_pending.unwrap_or(false) {
+        let replaced = PENDING_PAYOUTS
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter(|item| !matches!(item, Ok((_, p)) if p.requester != info.sender))
+            .map(|item| item.map(|(seq, _)| seq))
+            .collect::<StdResult<Vec<_>>>()?;
+        for seq in replaced {
+            close_pending(deps.storage, &env.block, seq, PayoutStatus::Cancelled)?;
+            resp = resp.add_attribute("cancelled_request_id", seq.to_string());
+        }
+    }
+    ensure!(
+        PENDING_PAYOUTS.keys(deps.storage, None, None, Order::Ascending).count() < MAX_PENDING_PAYOUTS,
+        ContractError::TooManyPendingPayouts {}
+    );
+
+    // reloaded so that allowance released by replaced payouts is taken into account
+    let mut initiator = load_initiator(deps.storage, &env.block, &info.sender)?;
+    let mut amount = resolve_amount_in_quote(deps.as_ref(), &env, amount_in_quote)?;
+    if let Some(limit) = initiator.request_limit() {
+        ensure!(
+            !limit.is_zero() && (amount_in_quote.is_none() || amount <= limit),
+            ContractError::InitiatorLimitExceeded { requested: amount, limit }
+        );
+        amount = amount.min(limit);
     }
+    initiator.used += amount;
+    INITIATORS.save(deps.storage, &info.sender, &initiator)?;
 
-    let amount = resolve_amount_in_quote(deps.as_ref(), &env, amount_in_quote)?;
     let pending = PendingPayout {
+        seq: 0,
+        requester: info.sender,
         amount_in_quote: amount,
-        expires_at: expires_at.clone() // todo
-    };
-
-    PENDING_PAYOUT.save(deps.storage, &pending)?;
-
-    let oracle_msg = OracleExecuteMsg::RequestPrice {
-        base: cfg.settlement_asset_limit.denom.clone(), // todo
-        quote: cfg.quote_asset_limit.denom.clone(),
-        expiration: expires_at.clone(),
-        valid_from: None,
-        sequence: None,
+        expires_at,
+        price_band,
+        created_at: env.block.time,
+        attempts: 1,
+        retry_at: None,
     };
-    let sub = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: cfg.price_feeder_addr.to_string(),
-        msg: to_json_binary(&oracle_msg)?,
-        funds: vec![],
-    });
+    let (seq, sub) = queue_payout(deps.storage, &cfg, pending)?;
 
-    Ok(Response::new()
+    Ok(resp
         .add_message(sub)
         .add_attribute("action", "request_payout")
+        .add_attribute("request_id", seq.to_string())
         .add_attribute(
             "amount_in_quote",
             amount_in_quote
@@ -156,48 +285,87 @@ fn execute_request_payout(
     )
 }
 
+/// Stores `pending` under the next payout sequence, reserves its quote amount and
+/// builds its oracle price request.
+pub(crate) fn queue_payout(
+    storage: &mut dyn Storage,
+    cfg: &Config,
+    mut pending: PendingPayout,
+) -> Result<(u64, CosmosMsg), ContractError> {
+    let seq = PAYOUT_SEQ.may_load(storage)?.unwrap_or_default() + 1;
+    PAYOUT_SEQ.save(storage, &seq)?;
+    pending.seq = seq;
+
+    PENDING_PAYOUTS.save(storage, seq, &pending)?;
+    BUDGET_USAGE.update(storage, |mut usage| -> StdResult<_> {
+        usage.quote_reserved += pending.amount_in_quote;
+        Ok(usage)
+    })?;
+
+    Ok((seq, price_request_msg(cfg, &pending)?))
+}
+
+/// Oracle price request for `pending`, echoing its sequence back as `request_id`.
+/// Under `cached_price_age` the price may be that much older than the request, so the
+/// oracle can answer from its cache within the same transaction.
+pub(crate) fn price_request_msg(cfg: &Config, pending: &PendingPayout) -> StdResult<CosmosMsg> {
+    let oracle_msg = OracleExecuteMsg::RequestPrice {
+        base: cfg.settlement_asset.id(),
+        quote: cfg.quote_asset_limit.denom.clone(),
+        expiration: pending.expires_at,
+        valid_from: cfg
+            .cached_price_age
+            .map(|age| Expiration::AtTime(pending.created_at.minus_seconds(age))),
+        sequence: Some(pending.seq),
+    };
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: cfg.price_feeder_addr.to_string(),
+        msg: to_json_binary(&oracle_msg)?,
+        funds: vec![],
+    }))
+}
+
 fn resolve_amount_in_quote(
     deps: Deps,
     env: &Env,
     amount_in_quote: Option<Uint128>,
-) -> StdResult<Uint128> {
-    if let Some(a) = amount_in_quote {
-        return Ok(a);
-    }
-
+) -> Result<Uint128, ContractError> {
     let cfg = CONFIG.load(deps.storage)?;
-    // todo:
-
-    // let bal: BalanceResponse = deps.querier.query(
-    //     &QueryRequest::Bank(BankQuery::Balance {
-    //         address: env.contract.address.to_string(),
-    //         denom: cfg.quote_asset_limit.denom.clone(),
-    //     })
-    // )?;
-    //
-    // let mut amount = bal.amount.amount;
-    //
-    // let cap: Uint128 = cfg.quote_asset_limit.amount;
-    // if amount > cap {
-    //     amount = cap;
-    // }
-    //
-    // Ok(amount)
-    unimplemented!()
+    let usage = BUDGET_USAGE.may_load(deps.storage)?.unwrap_or_default();
+    let remaining = usage.quote_available(&cfg);
+    ensure!(
+        !remaining.is_zero() && !usage.settlement_remaining(&cfg).is_zero(),
+        ContractError::BudgetExhausted {}
+    );
+    let balance = cfg
+        .settlement_asset
+        .query_balance(&deps.querier, &env.contract.address)?;
+    ensure!(!balance.is_zero(), ContractError::NoSettlementBalance {});
+
+    match amount_in_quote {
+        Some(a) => {
+            ensure!(
+                a <= remaining,
+                ContractError::QuoteBudgetExceeded { requested: a, remaining }
+            );
+            Ok(a)
+        }
+        // "all available": request the whole unreserved quote budget,
+        // settlement trims it to the balance and the settlement budget
+        None => Ok(remaining),
+    }
 }
 
-fn normalize_duration_to_expiration(
+pub(crate) fn normalize_duration_to_expiration(
     env: &Env,
     bounds: &DurationBounds,
     duration: Option<Duration>,
 ) -> StdResult<Expiration> {
-    let d = duration.unwrap_or_else(|| {
-        // todo: do we need to use default blocks or seconds? mb prioritize to seconds?
-        if bounds.default.blocks > 0 {
-            Duration::Blocks(bounds.default.blocks)
-        } else {
-            Duration::Seconds(bounds.default.seconds)
-        }
+    // todo: do we need to use default blocks or seconds? mb prioritize to seconds?
+    let d = duration.unwrap_or(if bounds.default.blocks > 0 {
+        Duration::Blocks(bounds.default.blocks)
+    } else {
+        Duration::Seconds(bounds.default.seconds)
     });
 
     // todo: check StdError
@@ -224,36 +392,95 @@ fn normalize_duration_to_expiration(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<Binary> {
-    unimplemented!()
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        ibc::IBC_TRANSFER_REPLY_ID => ibc::transfer_reply(deps, msg),
+        id => Err(StdError::generic_err(format!("unknown reply id: {id}")).into()),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_json_binary(&query::config(deps)?),
+        QueryMsg::Status {} => to_json_binary(&query::status(deps)?),
+        QueryMsg::Budget {} => to_json_binary(&query::budget(deps)?),
+        QueryMsg::AvailableBalance {} => to_json_binary(&query::available_balance(deps, env)?),
+        QueryMsg::PendingPayout { id } => to_json_binary(&query::pending_payout(deps, id)?),
+        QueryMsg::PendingPayouts { start_after, limit } => {
+            to_json_binary(&query::pending_payouts(deps, start_after, limit)?)
+        }
+        QueryMsg::Schedules { start_after, limit } => {
+            to_json_binary(&query::schedules(deps, start_after, limit)?)
+        }
+        QueryMsg::Initiators { start_after, limit } => {
+            to_json_binary(&query::initiators(deps, start_after, limit)?)
+        }
+        QueryMsg::SimulatePayout { amount_in_quote, price } => {
+            to_json_binary(&query::simulate_payout(deps, env, amount_in_quote, price)?)
+        }
+        QueryMsg::Funders { start_after, limit } => {
+            to_json_binary(&query::funders(deps, start_after, limit)?)
+        }
+        QueryMsg::Payout { id } => to_json_binary(&query::payout(deps, id)?),
+        QueryMsg::Payouts {
+            start_after,
+            limit,
+            status,
+            since,
+            until,
+        } => to_json_binary(&query::payouts(deps, start_after, limit, status, since, until)?),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use cosmwasm_std::{
-        testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier},
-        from_binary, CosmosMsg, WasmMsg, Uint128, Addr, MemoryStorage, OwnedDeps,
+        testing::{message_info, mock_dependencies, mock_env, MockApi, MockQuerier},
+        coin, coins, from_json, Addr, BankMsg, Decimal, MemoryStorage, OwnedDeps,
     };
+    use drip_disburser_interface::msg::{BudgetResponse, FundersResponse, RecipientWeight, StatusResponse};
 
     fn seed_config(deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>) {
         use crate::state::{Config, CONFIG};
         use drip_disburser_interface::msg::{DurationLimit, DurationBounds};
         let cfg = Config {
-            settlement_asset_limit: cosmwasm_std::coin(0, "SETTLE"),
-            quote_asset_limit: cosmwasm_std::coin(0, "QUOTE"),
+            settlement_asset: Asset::Native { denom: "SETTLE".to_string() },
+            settlement_asset_limit: Uint128::new(1_000_000),
+            quote_asset_limit: cosmwasm_std::coin(1_000_000, "QUOTE"),
             admin: Addr::unchecked("admin"),
+            refund_addr: Addr::unchecked("treasury"),
             recipient_addr: Addr::unchecked("recipient"),
+            recipients: vec![Recipient {
+                addr: "recipient".to_string(),
+                weight_bps: TOTAL_WEIGHT_BPS,
+                channel_id: None,
+            }],
             price_feeder_addr: Addr::unchecked("oracle"),
-            payment_initiator_addrs: vec![],
             funding_expiration: Default::default(),
             payout_duration_bounds: DurationBounds {
                 default: DurationLimit { blocks: 10, seconds: 30 },
                 min: Some(DurationLimit { blocks: 5, seconds: 10 }),
                 max: Some(DurationLimit { blocks: 60, seconds: 300 }),
             },
+            price_band: None,
+            max_price_age: Some(600),
+            cached_price_age: None,
+            quote_decimals: 0,
+            settlement_decimals: 0,
+            ibc_transfer_timeout: 600,
+            keeper_bounty: None,
+            retry_policy: None,
         };
         CONFIG.save(deps.as_mut().storage, &cfg).unwrap();
+        STATUS.save(deps.as_mut().storage, &LifecycleStatus::Active).unwrap();
+        TOTAL_FUNDED.save(deps.as_mut().storage, &Uint128::zero()).unwrap();
+        BUDGET_USAGE.save(deps.as_mut().storage, &BudgetUsage::default()).unwrap();
+        for initiator in ["caller", "other"] {
+            INITIATORS.save(deps.as_mut().storage, &Addr::unchecked(initiator), &Initiator::unlimited()).unwrap();
+        }
+        deps.querier.bank.update_balance(mock_env().contract.address, coins(1_000_000, "SETTLE"));
     }
 
     #[test]
@@ -264,7 +491,7 @@ mod tests {
 
         env.block.height = 100;
 
-        let info = mock_info("caller", &[]);
+        let info = message_info(&Addr::unchecked("caller"), &[]);
         let amount = Some(Uint128::new(123));
         let duration = Some(Duration::Blocks(7));
         let replace = Some(false);
@@ -276,6 +503,7 @@ mod tests {
             amount,
             duration,
             replace,
+            PriceBand::default(),
         ).expect("ok");
 
         assert_eq!(resp.messages.len(), 1);
@@ -284,14 +512,14 @@ mod tests {
             CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }) => {
                 assert_eq!(contract_addr, "oracle");
                 assert!(funds.is_empty());
-                let decoded: OracleExecuteMsg = from_binary(msg).unwrap();
+                let decoded: OracleExecuteMsg = from_json(msg).unwrap();
                 match decoded {
                     OracleExecuteMsg::RequestPrice { base, quote, expiration, valid_from, sequence } => {
                         assert_eq!(base, "SETTLE");
                         assert_eq!(quote, "QUOTE");
                         assert_eq!(expiration, Expiration::AtHeight(107));
                         assert!(valid_from.is_none());
-                        assert!(sequence.is_none());
+                        assert_eq!(sequence, Some(1));
                     }
                     _ => panic!("unexpected oracle msg"),
                 }
@@ -299,8 +527,8 @@ mod tests {
             _ => panic!("unexpected CosmosMsg"),
         }
 
-        use crate::state::PENDING_PAYOUT;
-        let pending = PENDING_PAYOUT.load(&deps.storage).expect("pending");
+        let pending = PENDING_PAYOUTS.load(&deps.storage, 1).expect("pending");
+        assert_eq!(pending.seq, 1);
         assert_eq!(pending.amount_in_quote, Uint128::new(123));
         assert_eq!(pending.expires_at, Expiration::AtHeight(107));
     }
@@ -312,7 +540,7 @@ mod tests {
         seed_config(&mut deps);
 
         env.block.time = env.block.time.plus_seconds(1000);
-        let info = mock_info("caller", &[]);
+        let info = message_info(&Addr::unchecked("caller"), &[]);
         let resp = execute_request_payout(
             deps.as_mut(),
             env.clone(),
@@ -320,12 +548,13 @@ mod tests {
             Some(Uint128::new(1)),
             Some(Duration::Seconds(20)),
             None,
+            PriceBand::default(),
         ).unwrap();
 
         let msg = &resp.messages[0].msg;
         match msg {
             CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
-                let decoded: OracleExecuteMsg = from_binary(msg).unwrap();
+                let decoded: OracleExecuteMsg = from_json(msg).unwrap();
                 match decoded {
                     OracleExecuteMsg::RequestPrice { expiration, .. } => {
                         assert_eq!(expiration, Expiration::AtTime(env.block.time.plus_seconds(20)));
@@ -338,32 +567,43 @@ mod tests {
     }
 
     #[test]
-    fn request_payout_reject_when_pending_and_no_replace() {
+    fn request_payout_queues_concurrent_requests() {
         let mut deps = mock_dependencies();
         let env = mock_env();
         seed_config(&mut deps);
+        let request = |deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>, caller: &str, replace| {
+            execute_request_payout(
+                deps.as_mut(),
+                env.clone(),
+                message_info(&Addr::unchecked(caller), &[]),
+                Some(Uint128::new(400_000)),
+                Some(Duration::Blocks(6)),
+                Some(replace),
+                PriceBand::default(),
+            )
+        };
 
-        PENDING_PAYOUT.save(
-            deps.as_mut().storage,
-            &PendingPayout {
-                amount_in_quote: Uint128::new(5),
-                expires_at: Expiration::AtHeight(999),
-            },
-        ).unwrap();
+        request(&mut deps, "caller", false).unwrap();
+        request(&mut deps, "other", false).unwrap();
+        let pending: Vec<_> = PENDING_PAYOUTS
+            .keys(&deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(pending, vec![1, 2]);
 
-        let err = execute_request_payout(
-            deps.as_mut(),
-            env,
-            mock_info("caller", &[]),
-            Some(Uint128::new(7)),
-            Some(Duration::Blocks(6)),
-            Some(false), // replace_pending
-        ).unwrap_err();
+        // 800k of the 1M quote budget is reserved
+        let err = request(&mut deps, "caller", false).unwrap_err();
+        assert!(matches!(err, ContractError::QuoteBudgetExceeded { .. }));
 
-        match err {
-            ContractError::PendingAlreadyExists {} => {}
-            other => panic!("unexpected error: {other:?}"),
-        }
+        // replacing only cancels the caller's own request and frees its reservation
+        request(&mut deps, "caller", true).unwrap();
+        let pending: Vec<_> = PENDING_PAYOUTS
+            .keys(&deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(pending, vec![2, 3]);
+        assert_eq!(query::payout(deps.as_ref(), 1).unwrap().status, PayoutStatus::Cancelled);
+        assert_eq!(BUDGET_USAGE.load(&deps.storage).unwrap().quote_reserved, Uint128::new(800_000));
     }
 
     #[test]
@@ -386,4 +626,1631 @@ mod tests {
         let ok = normalize_duration_to_expiration(&env, &cfg.payout_duration_bounds, Some(Duration::Blocks(10))).unwrap();
         assert_eq!(ok, Expiration::AtHeight(env.block.height + 10));
     }
+
+    fn seed_pending(deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>, amount_in_quote: u128) {
+        let pending = PendingPayout {
+            seq: 3,
+            requester: Addr::unchecked("caller"),
+            amount_in_quote: Uint128::new(amount_in_quote),
+            expires_at: Expiration::AtHeight(mock_env().block.height + 10),
+            price_band: PriceBand::default(),
+            created_at: mock_env().block.time,
+            attempts: 1,
+            retry_at: None,
+        };
+        PENDING_PAYOUTS.save(deps.as_mut().storage, pending.seq, &pending).unwrap();
+        BUDGET_USAGE.update(deps.as_mut().storage, |mut usage| -> StdResult<_> {
+            usage.quote_reserved += pending.amount_in_quote;
+            Ok(usage)
+        }).unwrap();
+    }
+
+    fn oracle_callback(price: Decimal, request_id: u64) -> ExecuteMsg {
+        ExecuteMsg::OnPayoutResponse {
+            price,
+            price_timestamp: mock_env().block.time.seconds(),
+            request_id,
+        }
+    }
+
+    #[test]
+    fn payout_response_settles_to_recipient() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(500, "SETTLE"));
+        seed_pending(&mut deps, 100);
+
+        // 100 QUOTE at 0.5 QUOTE per SETTLE
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::percent(50), 3),
+        ).unwrap();
+
+        assert_eq!(resp.messages.len(), 1);
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "recipient".to_string(),
+                amount: coins(200, "SETTLE"),
+            })
+        );
+        assert!(PENDING_PAYOUTS.is_empty(&deps.storage));
+
+        // capped by the contract balance
+        seed_pending(&mut deps, 1_000);
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::one(), 3),
+        ).unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "recipient".to_string(),
+                amount: coins(500, "SETTLE"),
+            })
+        );
+    }
+
+    #[test]
+    fn payout_scales_between_asset_decimals() {
+        use cosmwasm_std::{DenomMetadata, DenomUnit};
+        use drip_disburser_interface::msg::{DurationBounds, DurationLimit, SettlementAsset};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let metadata = |base: &str, display: &str, exponent| DenomMetadata {
+            base: base.to_string(),
+            display: display.to_string(),
+            denom_units: vec![
+                DenomUnit { denom: base.to_string(), exponent: 0, aliases: vec![] },
+                DenomUnit { denom: display.to_string(), exponent, aliases: vec![] },
+            ],
+            ..Default::default()
+        };
+        deps.querier.bank.set_denom_metadata(&[metadata("uusdc", "usdc", 6), metadata("aevmos", "evmos", 18)]);
+        deps.querier.bank.update_balance(&env.contract.address, coins(10u128.pow(20), "aevmos"));
+        let oracle = deps.api.addr_make("oracle");
+        let msg = InstantiateMsg {
+            settlement_asset: SettlementAsset::Native { denom: "aevmos".to_string() },
+            settlement_asset_limit: Uint128::new(10u128.pow(20)),
+            quote_asset_limit: coin(100_000_000, "uusdc"),
+            admin: None,
+            refund_addr: None,
+            recipient_addr: deps.api.addr_make("recipient").to_string(),
+            recipients: None,
+            oracle_addr: oracle.to_string(),
+            payment_initiator_addrs: vec![],
+            funding_expiration: Expiration::Never {},
+            payout_duration_bounds: DurationBounds {
+                default: DurationLimit { blocks: 10, seconds: 0 },
+                max: None,
+                min: None,
+            },
+            price_band: None,
+            max_price_age: None,
+            cached_price_age: None,
+            quote_decimals: None,
+            settlement_decimals: None,
+            ibc_transfer_timeout: None,
+            keeper_bounty: None,
+            retry_policy: None,
+            funder: None,
+        };
+        let admin = deps.api.addr_make("admin");
+        instantiate(deps.as_mut(), env.clone(), message_info(&admin, &[]), msg).unwrap();
+        let cfg = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!((cfg.quote_decimals, cfg.settlement_decimals), (6, 18));
+
+        // 1.5 USDC at 2 USDC per EVMOS buys 0.75 EVMOS
+        seed_pending(&mut deps, 1_500_000);
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&oracle, &[]),
+            oracle_callback(Decimal::percent(200), 3),
+        ).unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: cfg.recipient_addr.to_string(),
+                amount: coins(750_000_000_000_000_000, "aevmos"),
+            })
+        );
+        let budget = BUDGET_USAGE.load(&deps.storage).unwrap();
+        assert_eq!(budget.quote, Uint128::new(1_500_000));
+
+        // rounding favours the contract: 1 uusdc at 3 USDC per EVMOS buys
+        // 333333333333.33 aevmos, floored, worth a whole uusdc rounded up
+        seed_pending(&mut deps, 1);
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&oracle, &[]),
+            oracle_callback(Decimal::percent(300), 3),
+        ).unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: cfg.recipient_addr.to_string(),
+                amount: coins(333_333_333_333, "aevmos"),
+            })
+        );
+        assert_eq!(BUDGET_USAGE.load(&deps.storage).unwrap().quote, Uint128::new(1_500_001));
+    }
+
+    #[test]
+    fn payout_response_rejects_unknown_or_foreign_callbacks() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        seed_pending(&mut deps, 100);
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            oracle_callback(Decimal::one(), 3),
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let err = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::one(), 4),
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownPayoutRequest { request_id: 4 }));
+        assert!(PENDING_PAYOUTS.has(&deps.storage, 3));
+    }
+
+    #[test]
+    fn payout_response_clears_expired_pending() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(500, "SETTLE"));
+        seed_pending(&mut deps, 100);
+
+        env.block.height += 11;
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::one(), 3),
+        ).unwrap();
+
+        assert!(resp.messages.is_empty());
+        assert!(resp.attributes.iter().any(|a| a.key == "status" && a.value == "expired"));
+        assert!(PENDING_PAYOUTS.is_empty(&deps.storage));
+    }
+
+    #[test]
+    fn request_payout_respects_quote_budget() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        BUDGET_USAGE.save(
+            deps.as_mut().storage,
+            &BudgetUsage { quote: Uint128::new(999_990), ..Default::default() },
+        ).unwrap();
+
+        let err = execute_request_payout(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            Some(Uint128::new(11)),
+            None,
+            None,
+            PriceBand::default(),
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::QuoteBudgetExceeded { .. }));
+
+        // "all available" resolves to what is left of the quote budget
+        execute_request_payout(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("caller"), &[]),
+            None,
+            None,
+            None,
+            PriceBand::default(),
+        ).unwrap();
+        let pending = PENDING_PAYOUTS.load(&deps.storage, 1).unwrap();
+        assert_eq!(pending.amount_in_quote, Uint128::new(10));
+    }
+
+    #[test]
+    fn payout_response_trims_to_settlement_budget() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(10_000, "SETTLE"));
+        BUDGET_USAGE.save(
+            deps.as_mut().storage,
+            &BudgetUsage { quote: Uint128::new(100), settlement: Uint128::new(999_950), ..Default::default() },
+        ).unwrap();
+        seed_pending(&mut deps, 200);
+
+        // 200 QUOTE at 2 QUOTE per SETTLE would be 100 SETTLE, only 50 are left
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::percent(200), 3),
+        ).unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "recipient".to_string(),
+                amount: coins(50, "SETTLE"),
+            })
+        );
+
+        let budget: BudgetResponse =
+            from_json(query(deps.as_ref(), env, QueryMsg::Budget {}).unwrap()).unwrap();
+        assert_eq!(budget.quote_used, Uint128::new(200));
+        assert_eq!(budget.quote_remaining, Uint128::new(999_800));
+        assert_eq!(budget.settlement_used, Uint128::new(1_000_000));
+        assert!(budget.settlement_remaining.is_zero());
+    }
+
+    #[test]
+    fn payout_history_records_every_outcome() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(10_000, "SETTLE"));
+        let request = |deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>, env: &Env, replace| {
+            execute_request_payout(
+                deps.as_mut(),
+                env.clone(),
+                message_info(&Addr::unchecked("caller"), &[]),
+                Some(Uint128::new(100)),
+                Some(Duration::Blocks(10)),
+                Some(replace),
+                PriceBand::default(),
+            ).unwrap();
+        };
+        let callback = |deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>, env: &Env, id| {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                message_info(&Addr::unchecked("oracle"), &[]),
+                oracle_callback(Decimal::one(), id),
+            ).unwrap();
+        };
+
+        // #1 replaced by #2, which settles
+        request(&mut deps, &env, false);
+        request(&mut deps, &env, true);
+        callback(&mut deps, &env, 2);
+        // #3 expires before the price arrives
+        env.block.time = env.block.time.plus_seconds(60);
+        request(&mut deps, &env, false);
+        env.block.height += 20;
+        callback(&mut deps, &env, 3);
+
+        let settled = query::payout(deps.as_ref(), 2).unwrap();
+        assert_eq!(settled.status, PayoutStatus::Settled);
+        assert_eq!(settled.requester, Addr::unchecked("caller"));
+        assert_eq!(settled.price, Some(Decimal::one()));
+        assert_eq!(settled.settlement_amount, Uint128::new(100));
+
+        let all = query::payouts(deps.as_ref(), None, None, None, None, None).unwrap().payouts;
+        let statuses: Vec<_> = all.iter().map(|p| (p.id, p.status.clone())).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (1, PayoutStatus::Cancelled),
+                (2, PayoutStatus::Settled),
+                (3, PayoutStatus::TimedOut),
+            ]
+        );
+
+        let page = query::payouts(deps.as_ref(), Some(1), Some(1), None, None, None).unwrap().payouts;
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, 2);
+
+        let timed_out = query::payouts(deps.as_ref(), None, None, Some(PayoutStatus::TimedOut), None, None)
+            .unwrap()
+            .payouts;
+        assert_eq!(timed_out.len(), 1);
+        assert_eq!(timed_out[0].id, 3);
+
+        let early = query::payouts(deps.as_ref(), None, None, None, None, Some(env.block.time))
+            .unwrap()
+            .payouts;
+        assert_eq!(early.len(), 2);
+    }
+
+    #[test]
+    fn filtered_payout_pages_walk_the_indexes() {
+        let mut deps = mock_dependencies();
+        let start = mock_env().block;
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&mock_env().contract.address, coins(1_000, "SETTLE"));
+        let ledger = [
+            (10, PayoutStatus::Settled),
+            (30, PayoutStatus::Failed),
+            (20, PayoutStatus::Settled),
+            (20, PayoutStatus::Settled),
+            (40, PayoutStatus::TimedOut),
+            (50, PayoutStatus::Settled),
+        ];
+        for (id, (offset, status)) in (1..).zip(ledger) {
+            let mut env = mock_env();
+            env.block.height = start.height + offset;
+            env.block.time = start.time.plus_seconds(offset);
+            execute_request_payout(
+                deps.as_mut(),
+                env.clone(),
+                message_info(&Addr::unchecked("caller"), &[]),
+                Some(Uint128::new(100)),
+                None,
+                None,
+                PriceBand::default(),
+            ).unwrap();
+            let msg = match status {
+                PayoutStatus::TimedOut => ExecuteMsg::OnPayoutTimeout { request_id: id },
+                PayoutStatus::Failed => ExecuteMsg::OnPayoutResponse {
+                    price: Decimal::zero(),
+                    price_timestamp: env.block.time.seconds(),
+                    request_id: id,
+                },
+                _ => ExecuteMsg::OnPayoutResponse {
+                    price: Decimal::one(),
+                    price_timestamp: env.block.time.seconds(),
+                    request_id: id,
+                },
+            };
+            execute(deps.as_mut(), env, message_info(&Addr::unchecked("oracle"), &[]), msg).unwrap();
+            assert_eq!(query::payout(deps.as_ref(), id).unwrap().status, status);
+        }
+
+        let ids = |status: Option<PayoutStatus>, start_after, limit, since: Option<u64>, until: Option<u64>| {
+            query::payouts(
+                deps.as_ref(),
+                start_after,
+                limit,
+                status,
+                since.map(|offset| start.time.plus_seconds(offset)),
+                until.map(|offset| start.time.plus_seconds(offset)),
+            )
+            .unwrap()
+            .payouts
+            .into_iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>()
+        };
+        // unfiltered pages follow the ids
+        assert_eq!(ids(None, Some(2), Some(2), None, None), vec![3, 4]);
+        // filtered pages follow the time, then the id
+        assert_eq!(ids(Some(PayoutStatus::Settled), None, Some(2), None, None), vec![1, 3]);
+        assert_eq!(ids(Some(PayoutStatus::Settled), Some(3), Some(2), None, None), vec![4, 6]);
+        assert_eq!(ids(None, None, None, Some(20), Some(50)), vec![3, 4, 2, 5]);
+        assert_eq!(ids(Some(PayoutStatus::Settled), None, None, Some(20), Some(50)), vec![3, 4]);
+        // a cursor before `since` starts at `since`
+        assert_eq!(ids(None, Some(1), Some(3), Some(20), None), vec![3, 4, 2]);
+        assert_eq!(ids(None, Some(4), None, Some(20), Some(50)), vec![2, 5]);
+    }
+
+    #[test]
+    fn terminate_cancels_pending_and_refunds() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(
+            &env.contract.address,
+            vec![cosmwasm_std::coin(700, "SETTLE"), cosmwasm_std::coin(5, "QUOTE")],
+        );
+        seed_pending(&mut deps, 100);
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            ExecuteMsg::Terminate {},
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::Terminate {},
+        ).unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: vec![cosmwasm_std::coin(5, "QUOTE"), cosmwasm_std::coin(700, "SETTLE")],
+            })
+        );
+        assert!(PENDING_PAYOUTS.is_empty(&deps.storage));
+        assert_eq!(query::payout(deps.as_ref(), 3).unwrap().status, PayoutStatus::Cancelled);
+
+        let status: StatusResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::Status {}).unwrap()).unwrap();
+        assert_eq!(status.status, LifecycleStatus::Terminated);
+
+        let err = execute_request_payout(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("caller"), &[]),
+            Some(Uint128::new(1)),
+            None,
+            None,
+            PriceBand::default(),
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidStatus { status: LifecycleStatus::Terminated }));
+    }
+
+    #[test]
+    fn closed_disburser_rejects_config_and_schedule_changes() {
+        use cw_utils::Scheduled;
+        use drip_disburser_interface::msg::{MissedPeriods, Schedule};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        let admin = message_info(&Addr::unchecked("admin"), &[]);
+        execute(deps.as_mut(), env.clone(), admin.clone(), ExecuteMsg::Terminate {}).unwrap();
+
+        let msgs = vec![
+            ExecuteMsg::UpdateRecipients { recipients: vec![], dust_recipient: None },
+            ExecuteMsg::AddPaymentInitiator {
+                addr: deps.api.addr_make("initiator").to_string(),
+                allowance: None,
+                expires_at: None,
+                max_per_request: None,
+            },
+            ExecuteMsg::RemovePaymentInitiator { addr: "caller".to_string() },
+            ExecuteMsg::UpdatePriceBand { price_band: None },
+            ExecuteMsg::UpdateCachedPriceAge { cached_price_age: Some(60) },
+            ExecuteMsg::UpdateKeeperBounty { keeper_bounty: None },
+            ExecuteMsg::UpdateRetryPolicy { retry_policy: None },
+            ExecuteMsg::AddSchedule {
+                schedule: Schedule {
+                    amount_in_quote: Uint128::new(100),
+                    interval: Duration::Seconds(1_000),
+                    start: Scheduled::AtTime(env.block.time),
+                    end: None,
+                    missed_periods: MissedPeriods::Skip,
+                },
+            },
+            ExecuteMsg::RemoveSchedule { id: 1 },
+        ];
+        for msg in msgs {
+            let err = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidStatus { status: LifecycleStatus::Terminated }));
+        }
+    }
+
+    #[test]
+    fn paused_disburser_rejects_requests_until_resumed() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        let admin = message_info(&Addr::unchecked("admin"), &[]);
+
+        execute(deps.as_mut(), env.clone(), admin.clone(), ExecuteMsg::Pause {}).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), admin.clone(), ExecuteMsg::Pause {}).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidStatus { status: LifecycleStatus::Paused }));
+
+        let err = execute_request_payout(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            Some(Uint128::new(1)),
+            None,
+            None,
+            PriceBand::default(),
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidStatus { status: LifecycleStatus::Paused }));
+
+        execute(deps.as_mut(), env.clone(), admin, ExecuteMsg::Resume {}).unwrap();
+        execute_request_payout(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("caller"), &[]),
+            Some(Uint128::new(1)),
+            None,
+            None,
+            PriceBand::default(),
+        ).unwrap();
+    }
+
+    #[test]
+    fn price_arriving_while_paused_cancels_the_payout() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        let admin = message_info(&Addr::unchecked("admin"), &[]);
+        let initiator = Initiator { allowance: Some(Uint128::new(500)), ..Initiator::unlimited() };
+        INITIATORS.save(deps.as_mut().storage, &Addr::unchecked("caller"), &initiator).unwrap();
+        execute_request_payout(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            Some(Uint128::new(100)),
+            None,
+            None,
+            PriceBand::default(),
+        ).unwrap();
+        execute(deps.as_mut(), env.clone(), admin, ExecuteMsg::Pause {}).unwrap();
+
+        // the oracle would swallow an error, so the callback must succeed and clean up
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::one(), 1),
+        ).unwrap();
+        assert!(resp.messages.is_empty());
+        assert!(PENDING_PAYOUTS.is_empty(&deps.storage));
+        let record = query::payout(deps.as_ref(), 1).unwrap();
+        assert_eq!(record.status, PayoutStatus::Cancelled);
+        assert_eq!(record.reason.as_deref(), Some("disburser paused"));
+        assert!(BUDGET_USAGE.load(&deps.storage).unwrap().quote_reserved.is_zero());
+        assert!(INITIATORS.load(&deps.storage, &Addr::unchecked("caller")).unwrap().used.is_zero());
+    }
+
+    #[test]
+    fn sweep_closes_out_expired_funding() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        seed_config(&mut deps);
+        CONFIG.update(deps.as_mut().storage, |mut cfg| -> StdResult<_> {
+            cfg.funding_expiration = Expiration::AtHeight(env.block.height + 100);
+            Ok(cfg)
+        }).unwrap();
+        deps.querier.bank.update_balance(&env.contract.address, coins(300, "SETTLE"));
+        let anyone = message_info(&Addr::unchecked("anyone"), &[]);
+
+        let err = execute(deps.as_mut(), env.clone(), anyone.clone(), ExecuteMsg::Sweep {}).unwrap_err();
+        assert!(matches!(err, ContractError::FundingNotExpired {}));
+
+        env.block.height += 100;
+        let err = execute_request_payout(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            Some(Uint128::new(1)),
+            None,
+            None,
+            PriceBand::default(),
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::FundingExpired {}));
+
+        let resp = execute(deps.as_mut(), env.clone(), anyone.clone(), ExecuteMsg::Sweep {}).unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".to_string(),
+                amount: coins(300, "SETTLE"),
+            })
+        );
+        assert_eq!(STATUS.load(&deps.storage).unwrap(), LifecycleStatus::Expired);
+
+        let err = execute(deps.as_mut(), env, anyone, ExecuteMsg::Sweep {}).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidStatus { status: LifecycleStatus::Expired }));
+    }
+
+    #[test]
+    fn funders_are_refunded_pro_rata() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&alice, &coins(10, "QUOTE")),
+            ExecuteMsg::Fund {},
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::Payment(_)));
+
+        for (funder, amount) in [(&alice, 300), (&bob, 100), (&alice, 200)] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                message_info(funder, &coins(amount, "SETTLE")),
+                ExecuteMsg::Fund {},
+            ).unwrap();
+        }
+
+        let funders: FundersResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::Funders { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert_eq!(funders.total_deposited, Uint128::new(600));
+        assert_eq!(funders.funders.len(), 2);
+
+        // 600 deposited, 199 left after payouts: alice gets 5/6, bob 1/6, dust to the treasury
+        deps.querier.bank.update_balance(&env.contract.address, coins(199, "SETTLE"));
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::Terminate {},
+        ).unwrap();
+        let sends: Vec<_> = resp.messages.iter().map(|m| m.msg.clone()).collect();
+        assert_eq!(sends.len(), 3);
+        for (to, amount) in [(alice.as_str(), 165), (bob.as_str(), 33), ("treasury", 1)] {
+            assert!(sends.contains(&CosmosMsg::Bank(BankMsg::Send {
+                to_address: to.to_string(),
+                amount: coins(amount, "SETTLE"),
+            })));
+        }
+    }
+
+    #[test]
+    fn direct_transfers_are_not_refunded_to_funders() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+
+        for (funder, amount) in [(&alice, 300), (&bob, 100)] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                message_info(funder, &coins(amount, "SETTLE")),
+                ExecuteMsg::Fund {},
+            ).unwrap();
+        }
+
+        // 400 deposited, 1_000 sent directly: funders get their deposits back, the rest the treasury
+        deps.querier.bank.update_balance(&env.contract.address, coins(1_400, "SETTLE"));
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::Terminate {},
+        ).unwrap();
+        let sends: Vec<_> = resp.messages.iter().map(|m| m.msg.clone()).collect();
+        assert_eq!(sends.len(), 3);
+        for (to, amount) in [(alice.as_str(), 300), (bob.as_str(), 100), ("treasury", 1_000)] {
+            assert!(sends.contains(&CosmosMsg::Bank(BankMsg::Send {
+                to_address: to.to_string(),
+                amount: coins(amount, "SETTLE"),
+            })));
+        }
+    }
+
+    #[test]
+    fn funders_are_capped_to_keep_the_close_out_bounded() {
+        use crate::state::MAX_FUNDERS;
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+
+        let funders: Vec<Addr> = (0..MAX_FUNDERS).map(|i| deps.api.addr_make(&format!("funder{i}"))).collect();
+        for funder in &funders {
+            execute(deps.as_mut(), env.clone(), message_info(funder, &coins(1, "SETTLE")), ExecuteMsg::Fund {})
+                .unwrap();
+        }
+
+        let latecomer = deps.api.addr_make("latecomer");
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&latecomer, &coins(1_000, "SETTLE")),
+            ExecuteMsg::Fund {},
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::TooManyFunders {}));
+
+        // known funders can still top up
+        execute(deps.as_mut(), env.clone(), message_info(&funders[0], &coins(9, "SETTLE")), ExecuteMsg::Fund {})
+            .unwrap();
+
+        deps.querier.bank.update_balance(&env.contract.address, coins(MAX_FUNDERS as u128 + 9, "SETTLE"));
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::Terminate {},
+        ).unwrap();
+        assert_eq!(resp.messages.len(), MAX_FUNDERS);
+        assert!(resp.messages.iter().any(|m| m.msg == CosmosMsg::Bank(BankMsg::Send {
+            to_address: funders[0].to_string(),
+            amount: coins(10, "SETTLE"),
+        })));
+    }
+
+    #[test]
+    fn settled_payout_is_split_between_weighted_recipients() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(10_000, "SETTLE"));
+        let alice = deps.api.addr_make("alice");
+        let bob = deps.api.addr_make("bob");
+        let carol = deps.api.addr_make("carol");
+        let weights = vec![
+            RecipientWeight { addr: alice.to_string(), weight_bps: 3_333, channel_id: None },
+            RecipientWeight { addr: bob.to_string(), weight_bps: 6_667, channel_id: None },
+        ];
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::UpdateRecipients {
+                recipients: vec![RecipientWeight { addr: alice.to_string(), weight_bps: 9_000, channel_id: None }],
+                dust_recipient: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidRecipients {}));
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::UpdateRecipients {
+                recipients: weights,
+                dust_recipient: Some(carol.to_string()),
+            },
+        ).unwrap();
+
+        seed_pending(&mut deps, 100);
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::one(), 3),
+        ).unwrap();
+
+        let sends: Vec<_> = resp.messages.iter().map(|m| m.msg.clone()).collect();
+        let expected: Vec<CosmosMsg> = [(&alice, 33), (&bob, 66), (&carol, 1)]
+            .into_iter()
+            .map(|(to, amount)| {
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: to.to_string(),
+                    amount: coins(amount, "SETTLE"),
+                })
+            })
+            .collect();
+        assert_eq!(sends, expected);
+        assert_eq!(query::payout(deps.as_ref(), 3).unwrap().recipients.len(), 3);
+    }
+
+    #[test]
+    fn initiator_allowance_and_limits_are_enforced() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        seed_config(&mut deps);
+        let operator = deps.api.addr_make("operator");
+        let request = |deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>, env: &Env, amount: Option<u128>, replace| {
+            execute_request_payout(
+                deps.as_mut(),
+                env.clone(),
+                message_info(&operator, &[]),
+                amount.map(Uint128::new),
+                None,
+                Some(replace),
+                PriceBand::default(),
+            )
+        };
+
+        let err = request(&mut deps, &env, Some(10), false).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::AddPaymentInitiator {
+                addr: operator.to_string(),
+                allowance: Some(Uint128::new(150)),
+                expires_at: Some(Expiration::AtHeight(env.block.height + 100)),
+                max_per_request: Some(Uint128::new(100)),
+            },
+        ).unwrap();
+
+        let err = request(&mut deps, &env, Some(101), false).unwrap_err();
+        assert!(matches!(err, ContractError::InitiatorLimitExceeded { .. }));
+
+        // "all available" is trimmed to the per-request maximum
+        request(&mut deps, &env, None, false).unwrap();
+        assert_eq!(PENDING_PAYOUTS.load(&deps.storage, 1).unwrap().amount_in_quote, Uint128::new(100));
+
+        // replacing the pending payout gives its amount back to the allowance
+        request(&mut deps, &env, Some(100), true).unwrap();
+        let err = request(&mut deps, &env, Some(100), false).unwrap_err();
+        assert!(matches!(err, ContractError::InitiatorLimitExceeded { .. }));
+        assert_eq!(INITIATORS.load(&deps.storage, &operator).unwrap().used, Uint128::new(100));
+
+        env.block.height += 100;
+        let err = request(&mut deps, &env, Some(10), false).unwrap_err();
+        assert!(matches!(err, ContractError::InitiatorExpired {}));
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::RemovePaymentInitiator { addr: operator.to_string() },
+        ).unwrap();
+        assert!(!INITIATORS.has(&deps.storage, &operator));
+    }
+
+    #[test]
+    fn out_of_band_price_fails_the_payout() {
+        use crate::state::payouts;
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(500, "SETTLE"));
+        let oracle = message_info(&Addr::unchecked("oracle"), &[]);
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            ExecuteMsg::RequestPayout {
+                amount_in_quote: Some(Uint128::new(100)),
+                duration_limit: None,
+                replace_pending: None,
+                min_price: Some(Decimal::percent(80)),
+                max_price: Some(Decimal::percent(20)),
+            },
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPriceBand {}));
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            ExecuteMsg::RequestPayout {
+                amount_in_quote: Some(Uint128::new(100)),
+                duration_limit: None,
+                replace_pending: None,
+                min_price: Some(Decimal::percent(40)),
+                max_price: None,
+            },
+        ).unwrap();
+        let resp = execute(deps.as_mut(), env.clone(), oracle.clone(), oracle_callback(Decimal::percent(25), 1)).unwrap();
+        assert!(resp.messages.is_empty());
+        let record = payouts().load(&deps.storage, 1).unwrap();
+        assert_eq!(record.status, PayoutStatus::Failed);
+        assert_eq!(record.price, Some(Decimal::percent(25)));
+        assert!(record.reason.is_some());
+        let usage = BUDGET_USAGE.load(&deps.storage).unwrap();
+        assert_eq!(usage.quote_reserved, Uint128::zero());
+        assert_eq!(usage.quote, Uint128::zero());
+
+        // the global band applies on top of the request band
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::UpdatePriceBand {
+                price_band: Some(PriceBand { min: None, max: Some(Decimal::one()) }),
+            },
+        ).unwrap();
+        seed_pending(&mut deps, 100);
+        execute(deps.as_mut(), env.clone(), oracle.clone(), oracle_callback(Decimal::percent(150), 3)).unwrap();
+        assert_eq!(payouts().load(&deps.storage, 3).unwrap().status, PayoutStatus::Failed);
+
+        seed_pending(&mut deps, 100);
+        let resp = execute(deps.as_mut(), env, oracle, oracle_callback(Decimal::one(), 3)).unwrap();
+        assert_eq!(resp.messages.len(), 1);
+        assert_eq!(payouts().load(&deps.storage, 3).unwrap().status, PayoutStatus::Settled);
+    }
+
+    #[test]
+    fn zero_price_fails_the_payout_and_releases_its_reservation() {
+        use crate::state::payouts;
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        let initiator = Initiator { allowance: Some(Uint128::new(500)), ..Initiator::unlimited() };
+        INITIATORS.save(deps.as_mut().storage, &Addr::unchecked("caller"), &initiator).unwrap();
+        execute_request_payout(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("caller"), &[]),
+            Some(Uint128::new(100)),
+            None,
+            None,
+            PriceBand::default(),
+        ).unwrap();
+
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::zero(), 1),
+        ).unwrap();
+        assert!(resp.messages.is_empty());
+        assert!(PENDING_PAYOUTS.is_empty(&deps.storage));
+        let record = payouts().load(&deps.storage, 1).unwrap();
+        assert_eq!(record.status, PayoutStatus::Failed);
+        assert_eq!(record.reason.as_deref(), Some("zero price"));
+        assert_eq!(BUDGET_USAGE.load(&deps.storage).unwrap().quote_reserved, Uint128::zero());
+        let used = INITIATORS.load(&deps.storage, &Addr::unchecked("caller")).unwrap().used;
+        assert_eq!(used, Uint128::zero());
+    }
+
+    #[test]
+    fn stale_price_fails_the_payout() {
+        use crate::state::payouts;
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(500, "SETTLE"));
+        let oracle = message_info(&Addr::unchecked("oracle"), &[]);
+        let requested_at = env.block.time.seconds();
+
+        // observed before the request was made
+        seed_pending(&mut deps, 100);
+        let msg = ExecuteMsg::OnPayoutResponse {
+            price: Decimal::one(),
+            price_timestamp: requested_at - 1,
+            request_id: 3,
+        };
+        let resp = execute(deps.as_mut(), env.clone(), oracle.clone(), msg).unwrap();
+        assert!(resp.messages.is_empty());
+        let record = payouts().load(&deps.storage, 3).unwrap();
+        assert_eq!(record.status, PayoutStatus::Failed);
+        assert_eq!(record.reason.as_deref(), Some("stale price"));
+        assert_eq!(BUDGET_USAGE.load(&deps.storage).unwrap().quote_reserved, Uint128::zero());
+
+        // older than max_price_age when it arrives
+        seed_pending(&mut deps, 100);
+        env.block.time = env.block.time.plus_seconds(601);
+        let msg = ExecuteMsg::OnPayoutResponse {
+            price: Decimal::one(),
+            price_timestamp: requested_at,
+            request_id: 3,
+        };
+        execute(deps.as_mut(), env.clone(), oracle.clone(), msg).unwrap();
+        assert_eq!(payouts().load(&deps.storage, 3).unwrap().status, PayoutStatus::Failed);
+
+        seed_pending(&mut deps, 100);
+        let msg = ExecuteMsg::OnPayoutResponse {
+            price: Decimal::one(),
+            price_timestamp: requested_at + 1,
+            request_id: 3,
+        };
+        let resp = execute(deps.as_mut(), env, oracle, msg).unwrap();
+        assert_eq!(resp.messages.len(), 1);
+        assert_eq!(payouts().load(&deps.storage, 3).unwrap().status, PayoutStatus::Settled);
+    }
+
+    #[test]
+    fn cw20_settlement_is_funded_and_paid_through_the_token_contract() {
+        use cosmwasm_std::{ContractResult, SystemResult, WasmQuery};
+        use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20ReceiveMsg};
+        use drip_disburser_interface::msg::ReceiveMsg;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        let token = deps.api.addr_make("token");
+        let funder = deps.api.addr_make("funder");
+        CONFIG
+            .update(deps.as_mut().storage, |mut cfg| -> StdResult<_> {
+                cfg.settlement_asset = Asset::Cw20 { contract_addr: token.clone() };
+                Ok(cfg)
+            })
+            .unwrap();
+        deps.querier.bank.update_balance(&env.contract.address, vec![]);
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { .. } => SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&BalanceResponse { balance: Uint128::new(500) }).unwrap(),
+            )),
+            _ => unimplemented!(),
+        });
+
+        // native deposits and foreign tokens are refused
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&funder, &coins(10, "SETTLE")),
+            ExecuteMsg::Fund {},
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSettlementAsset {}));
+        let deposit = Cw20ReceiveMsg {
+            sender: funder.to_string(),
+            amount: Uint128::new(500),
+            msg: to_json_binary(&ReceiveMsg::Fund {}).unwrap(),
+        };
+        let other_token = deps.api.addr_make("other_token");
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&other_token, &[]),
+            ExecuteMsg::Receive(deposit.clone()),
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSettlementAsset {}));
+
+        execute(deps.as_mut(), env.clone(), message_info(&token, &[]), ExecuteMsg::Receive(deposit)).unwrap();
+        let funders: FundersResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::Funders { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert_eq!(funders.total_deposited, Uint128::new(500));
+
+        seed_pending(&mut deps, 100);
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::percent(50), 3),
+        ).unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "recipient".to_string(),
+                    amount: Uint128::new(200),
+                }).unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // the queried token balance goes back to the funder on termination
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::Terminate {},
+        ).unwrap();
+        assert_eq!(resp.messages.len(), 1);
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: funder.to_string(),
+                    amount: Uint128::new(500),
+                }).unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn remote_recipient_is_paid_over_ics20_and_returned_on_timeout() {
+        use crate::ibc::{ibc_source_callback, IBC_TRANSFER_REPLY_ID};
+        use crate::state::{payouts, IBC_TRANSFERS};
+        use cosmwasm_std::{
+            IbcEndpoint, IbcMsg, IbcPacket, IbcSourceCallbackMsg, IbcTimeout, IbcTimeoutCallbackMsg,
+            MsgResponse, SubMsgResponse, SubMsgResult,
+        };
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(500, "SETTLE"));
+        let local = deps.api.addr_make("local");
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::UpdateRecipients {
+                recipients: vec![
+                    RecipientWeight { addr: local.to_string(), weight_bps: 5_000, channel_id: None },
+                    RecipientWeight {
+                        addr: "osmo1remote".to_string(),
+                        weight_bps: 5_000,
+                        channel_id: Some("channel-0".to_string()),
+                    },
+                ],
+                dust_recipient: None,
+            },
+        ).unwrap();
+
+        seed_pending(&mut deps, 100);
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("oracle"), &[]),
+            oracle_callback(Decimal::percent(50), 3),
+        ).unwrap();
+        assert_eq!(resp.messages.len(), 2);
+        let remote = &resp.messages[1];
+        assert_eq!(remote.id, IBC_TRANSFER_REPLY_ID);
+        match &remote.msg {
+            CosmosMsg::Ibc(IbcMsg::Transfer { channel_id, to_address, amount, timeout, memo }) => {
+                assert_eq!(channel_id, "channel-0");
+                assert_eq!(to_address, "osmo1remote");
+                assert_eq!(amount, &cosmwasm_std::coin(100, "SETTLE"));
+                assert_eq!(timeout.timestamp(), Some(env.block.time.plus_seconds(600)));
+                assert!(memo.as_deref().unwrap().contains("src_callback"));
+            }
+            msg => panic!("unexpected message {msg:?}"),
+        }
+
+        #[allow(deprecated)]
+        let reply_msg = Reply {
+            id: IBC_TRANSFER_REPLY_ID,
+            payload: remote.payload.clone(),
+            gas_used: 0,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+                // MsgTransferResponse { sequence: 7 }
+                msg_responses: vec![MsgResponse {
+                    type_url: "/ibc.applications.transfer.v1.MsgTransferResponse".to_string(),
+                    value: vec![0x08, 0x07].into(),
+                }],
+            }),
+        };
+        reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+        assert!(IBC_TRANSFERS.has(&deps.storage, ("channel-0", 7)));
+
+        let endpoint = |channel: &str| IbcEndpoint {
+            port_id: "transfer".to_string(),
+            channel_id: channel.to_string(),
+        };
+        let packet = IbcPacket::new(
+            Binary::default(),
+            endpoint("channel-0"),
+            endpoint("channel-1"),
+            7,
+            IbcTimeout::with_timestamp(env.block.time.plus_seconds(600)),
+        );
+        let timeout = IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg::new(packet, Addr::unchecked("relayer")));
+        ibc_source_callback(deps.as_mut(), env.clone(), timeout).unwrap();
+
+        assert!(!IBC_TRANSFERS.has(&deps.storage, ("channel-0", 7)));
+        let record = payouts().load(&deps.storage, 3).unwrap();
+        assert_eq!(record.status, PayoutStatus::Failed);
+        assert_eq!(record.returned, Uint128::new(100));
+        let usage = BUDGET_USAGE.load(&deps.storage).unwrap();
+        assert_eq!(usage.settlement, Uint128::new(100));
+        assert_eq!(usage.quote, Uint128::new(50));
+    }
+
+    #[test]
+    fn transfer_returned_after_termination_goes_to_the_refund_address() {
+        use crate::ibc::ibc_source_callback;
+        use crate::state::{payouts, IbcTransfer, IBC_TRANSFERS};
+        use drip_disburser_interface::msg::PayoutRecord;
+        use cosmwasm_std::{
+            IbcAckCallbackMsg, IbcAcknowledgement, IbcEndpoint, IbcPacket, IbcSourceCallbackMsg,
+            IbcTimeout,
+        };
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        seed_pending(&mut deps, 100);
+        let pending = PENDING_PAYOUTS.load(&deps.storage, 3).unwrap();
+        let record = PayoutRecord {
+            settlement_amount: Uint128::new(200),
+            ..pending.record(&env.block, PayoutStatus::Settled)
+        };
+        payouts().save(deps.as_mut().storage, 3, &record).unwrap();
+        PENDING_PAYOUTS.remove(deps.as_mut().storage, 3);
+        let transfer = IbcTransfer {
+            payout_id: 3,
+            requester: Addr::unchecked("caller"),
+            channel_id: "channel-0".to_string(),
+            amount: Uint128::new(200),
+            quote_amount: Uint128::new(100),
+        };
+        IBC_TRANSFERS.save(deps.as_mut().storage, ("channel-0", 7), &transfer).unwrap();
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&Addr::unchecked("admin"), &[]),
+            ExecuteMsg::Terminate {},
+        ).unwrap();
+
+        let endpoint = |channel: &str| IbcEndpoint {
+            port_id: "transfer".to_string(),
+            channel_id: channel.to_string(),
+        };
+        let packet = IbcPacket::new(
+            Binary::default(),
+            endpoint("channel-0"),
+            endpoint("channel-1"),
+            7,
+            IbcTimeout::with_timestamp(env.block.time.plus_seconds(600)),
+        );
+        let ack = IbcAcknowledgement::new(br#"{"error":"receiver rejected"}"#.as_slice());
+        let callback = IbcSourceCallbackMsg::Acknowledgement(IbcAckCallbackMsg::new(
+            ack,
+            packet,
+            Addr::unchecked("relayer"),
+        ));
+        let resp = ibc_source_callback(deps.as_mut(), env, callback).unwrap();
+
+        assert_eq!(resp.messages.len(), 1);
+        assert_eq!(resp.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(200, "SETTLE"),
+        }));
+        let record = payouts().load(&deps.storage, 3).unwrap();
+        assert_eq!(record.status, PayoutStatus::Failed);
+        assert_eq!(record.returned, Uint128::new(200));
+    }
+
+    #[test]
+    fn queries_expose_config_pending_payouts_and_simulation() {
+        use drip_disburser_interface::msg::{
+            AvailableBalanceResponse, ConfigResponse, InitiatorsResponse, PendingPayoutInfo,
+            PendingPayoutsResponse, SimulatePayoutResponse,
+        };
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        deps.querier.bank.update_balance(&env.contract.address, coins(500, "SETTLE"));
+        seed_pending(&mut deps, 100);
+
+        let cfg: ConfigResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(cfg.oracle_addr, Addr::unchecked("oracle"));
+        assert_eq!(cfg.recipients.len(), 1);
+
+        let pending: PendingPayoutsResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::PendingPayouts { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert_eq!(pending.payouts.len(), 1);
+        let single: PendingPayoutInfo =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::PendingPayout { id: 3 }).unwrap()).unwrap();
+        assert_eq!(single, pending.payouts[0]);
+        assert_eq!(single.amount_in_quote, Uint128::new(100));
+
+        let available: AvailableBalanceResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::AvailableBalance {}).unwrap()).unwrap();
+        assert_eq!(available.settlement_balance, Uint128::new(500));
+        assert_eq!(available.quote_available, Uint128::new(999_900));
+
+        let initiators: InitiatorsResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::Initiators { start_after: None, limit: Some(1) }).unwrap(),
+        ).unwrap();
+        assert_eq!(initiators.initiators.len(), 1);
+        assert_eq!(initiators.initiators[0].addr, Addr::unchecked("caller"));
+
+        // 400 QUOTE at 0.5 would be 800 SETTLE, trimmed to the 500 held
+        let sim: SimulatePayoutResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::SimulatePayout { amount_in_quote: Some(Uint128::new(400)), price: Decimal::percent(50) },
+            ).unwrap(),
+        ).unwrap();
+        assert_eq!(sim.settlement_amount, Uint128::new(500));
+        assert_eq!(sim.quote_paid, Uint128::new(250));
+        assert_eq!(sim.recipients[0].amount, Uint128::new(500));
+
+        assert!(query(
+            deps.as_ref(),
+            env,
+            QueryMsg::SimulatePayout { amount_in_quote: None, price: Decimal::zero() },
+        ).is_err());
+    }
+
+    #[test]
+    fn migrate_converts_legacy_withdrawal_ttl_layout() {
+        use crate::migrate::migrate;
+        use cosmwasm_std::Storage;
+        use cw2::{get_contract_version, set_contract_version};
+        use drip_disburser_interface::msg::{DurationLimit, MigrateMsg};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let legacy = r#"{
+            "settlement_asset_limit": { "amount": "1000", "denom": "SETTLE" },
+            "quote_asset_limit": { "amount": "2000", "denom": "QUOTE" },
+            "admin": "admin",
+            "recipient_addr": "recipient",
+            "price_feeder_addr": "oracle",
+            "payment_initiator_addrs": ["caller"],
+            "funding_expiration": { "never": {} },
+            "withdrawal_ttl": { "default_sec": 60, "max_sec": 300 }
+        }"#;
+        deps.storage.set(b"config", legacy.as_bytes());
+        deps.storage.set(b"pending_payout", b"{}");
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "0.0.1").unwrap();
+
+        migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+        let cfg = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(cfg.settlement_asset, Asset::Native { denom: "SETTLE".to_string() });
+        assert_eq!(cfg.settlement_asset_limit, Uint128::new(1000));
+        assert_eq!(cfg.refund_addr, Addr::unchecked("admin"));
+        assert_eq!(
+            cfg.payout_duration_bounds,
+            DurationBounds {
+                default: DurationLimit { blocks: 0, seconds: 60 },
+                max: Some(DurationLimit { blocks: 0, seconds: 300 }),
+                min: None,
+            }
+        );
+        assert!(INITIATORS.has(&deps.storage, &Addr::unchecked("caller")));
+        assert_eq!(STATUS.load(&deps.storage).unwrap(), LifecycleStatus::Active);
+        assert!(deps.storage.get(b"pending_payout").is_none());
+        assert_eq!(get_contract_version(&deps.storage).unwrap().version, CONTRACT_VERSION);
+
+        // a current layout only bumps the version
+        migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+        set_contract_version(&mut deps.storage, CONTRACT_NAME, "99.0.0").unwrap();
+        assert!(migrate(deps.as_mut(), env.clone(), MigrateMsg {}).is_err());
+        set_contract_version(&mut deps.storage, "crates.io:other", "0.0.1").unwrap();
+        assert!(migrate(deps.as_mut(), env, MigrateMsg {}).is_err());
+    }
+
+    #[test]
+    fn crank_requests_due_scheduled_payouts() {
+        use cw_utils::Scheduled;
+        use drip_disburser_interface::msg::{MissedPeriods, Schedule, SchedulesResponse};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        seed_config(&mut deps);
+        let admin = message_info(&Addr::unchecked("admin"), &[]);
+        let keeper = message_info(&Addr::unchecked("anyone"), &[]);
+        let start = env.block.time;
+        let schedule = |missed_periods| Schedule {
+            amount_in_quote: Uint128::new(100),
+            interval: Duration::Seconds(1_000),
+            start: Scheduled::AtTime(start),
+            end: Some(Scheduled::AtTime(start.plus_seconds(10_000))),
+            missed_periods,
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            admin.clone(),
+            ExecuteMsg::AddSchedule {
+                schedule: Schedule { start: Scheduled::AtHeight(1), ..schedule(MissedPeriods::Skip) },
+            },
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSchedule {}));
+        execute(deps.as_mut(), env.clone(), admin.clone(), ExecuteMsg::AddSchedule { schedule: schedule(MissedPeriods::Accumulate) }).unwrap();
+        execute(deps.as_mut(), env.clone(), admin, ExecuteMsg::AddSchedule { schedule: schedule(MissedPeriods::Skip) }).unwrap();
+
+        // the first period of both schedules is due right away
+        let resp = execute(deps.as_mut(), env.clone(), keeper.clone(), ExecuteMsg::Crank {}).unwrap();
+        assert_eq!(resp.messages.len(), 2);
+        let pending = PENDING_PAYOUTS.load(&deps.storage, 1).unwrap();
+        assert_eq!(pending.requester, env.contract.address);
+        assert_eq!(pending.amount_in_quote, Uint128::new(100));
+
+        let err = execute(deps.as_mut(), env.clone(), keeper.clone(), ExecuteMsg::Crank {}).unwrap_err();
+        assert!(matches!(err, ContractError::NothingDue {}));
+
+        // three more periods have started: accumulated versus skipped
+        env.block.time = start.plus_seconds(3_500);
+        execute(deps.as_mut(), env.clone(), keeper.clone(), ExecuteMsg::Crank {}).unwrap();
+        assert_eq!(PENDING_PAYOUTS.load(&deps.storage, 3).unwrap().amount_in_quote, Uint128::new(300));
+        assert_eq!(PENDING_PAYOUTS.load(&deps.storage, 4).unwrap().amount_in_quote, Uint128::new(100));
+        let schedules: SchedulesResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::Schedules { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert_eq!(schedules.schedules[0].next_due, start.plus_seconds(4_000).seconds());
+
+        // periods starting at or after the end are not paid, and finished schedules go away
+        env.block.time = start.plus_seconds(50_000);
+        execute(deps.as_mut(), env.clone(), keeper.clone(), ExecuteMsg::Crank {}).unwrap();
+        assert_eq!(PENDING_PAYOUTS.load(&deps.storage, 5).unwrap().amount_in_quote, Uint128::new(600));
+        let schedules: SchedulesResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::Schedules { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert!(schedules.schedules.is_empty());
+    }
+
+    #[test]
+    fn keepers_are_rewarded_up_to_the_block_cap() {
+        use cw_utils::Scheduled;
+        use drip_disburser_interface::msg::{KeeperBounty, MissedPeriods, Schedule};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        seed_config(&mut deps);
+        let admin = message_info(&Addr::unchecked("admin"), &[]);
+        let keeper = message_info(&Addr::unchecked("keeper"), &[]);
+        deps.querier.bank.update_balance(
+            &env.contract.address,
+            vec![coin(100, "BOUNTY"), coin(1_000_000, "SETTLE")],
+        );
+
+        let bounty = |denom: &str| KeeperBounty {
+            reward: coin(10, denom),
+            max_units_per_block: 3,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            admin.clone(),
+            ExecuteMsg::UpdateKeeperBounty { keeper_bounty: Some(bounty("SETTLE")) },
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidKeeperBounty {}));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin.clone(),
+            ExecuteMsg::UpdateKeeperBounty { keeper_bounty: Some(bounty("BOUNTY")) },
+        ).unwrap();
+
+        // four schedules are due but only three units are rewarded in a block
+        for _ in 0..4 {
+            let schedule = Schedule {
+                amount_in_quote: Uint128::new(100),
+                interval: Duration::Seconds(1_000),
+                start: Scheduled::AtTime(env.block.time),
+                end: None,
+                missed_periods: MissedPeriods::Skip,
+            };
+            execute(deps.as_mut(), env.clone(), admin.clone(), ExecuteMsg::AddSchedule { schedule }).unwrap();
+        }
+        let resp = execute(deps.as_mut(), env.clone(), keeper.clone(), ExecuteMsg::Crank {}).unwrap();
+        assert_eq!(resp.messages.len(), 5);
+        assert_eq!(
+            resp.messages[4].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "keeper".to_string(),
+                amount: coins(30, "BOUNTY"),
+            })
+        );
+
+        // the sweep earns one unit and the rest of the pool is refunded
+        CONFIG.update(deps.as_mut().storage, |mut cfg| -> StdResult<_> {
+            cfg.funding_expiration = Expiration::AtHeight(env.block.height + 1);
+            Ok(cfg)
+        }).unwrap();
+        env.block.height += 1;
+        let resp = execute(deps.as_mut(), env, keeper, ExecuteMsg::Sweep {}).unwrap();
+        let sends = resp
+            .messages
+            .iter()
+            .filter_map(|m| match &m.msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => Some((to_address.as_str(), amount.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sends,
+            vec![
+                ("treasury", vec![coin(90, "BOUNTY"), coin(1_000_000, "SETTLE")]),
+                ("keeper", coins(10, "BOUNTY")),
+            ]
+        );
+    }
+
+    #[test]
+    fn cranking_an_exhausted_schedule_earns_no_bounty() {
+        use cw_utils::Scheduled;
+        use drip_disburser_interface::msg::{KeeperBounty, MissedPeriods, Schedule};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        let admin = message_info(&Addr::unchecked("admin"), &[]);
+        deps.querier.bank.update_balance(
+            &env.contract.address,
+            vec![coin(100, "BOUNTY"), coin(1_000_000, "SETTLE")],
+        );
+        let keeper_bounty = KeeperBounty { reward: coin(10, "BOUNTY"), max_units_per_block: 3 };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin.clone(),
+            ExecuteMsg::UpdateKeeperBounty { keeper_bounty: Some(keeper_bounty) },
+        ).unwrap();
+        let schedule = Schedule {
+            amount_in_quote: Uint128::new(100),
+            interval: Duration::Seconds(1_000),
+            start: Scheduled::AtTime(env.block.time),
+            end: None,
+            missed_periods: MissedPeriods::Skip,
+        };
+        execute(deps.as_mut(), env.clone(), admin, ExecuteMsg::AddSchedule { schedule }).unwrap();
+        BUDGET_USAGE.update(deps.as_mut().storage, |mut usage| -> StdResult<_> {
+            usage.quote = Uint128::new(1_000_000);
+            Ok(usage)
+        }).unwrap();
+
+        // the schedule advances, but without a payout to request the keeper is not paid
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            message_info(&Addr::unchecked("keeper"), &[]),
+            ExecuteMsg::Crank {},
+        ).unwrap();
+        assert!(resp.messages.is_empty());
+        assert!(resp.attributes.iter().any(|a| a.key == "exhausted_schedule_id"));
+    }
+
+    #[test]
+    fn timed_out_price_requests_are_retried_then_closed() {
+        use drip_disburser_interface::msg::{PendingPayoutInfo, RetryPolicy};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        seed_config(&mut deps);
+        INITIATORS.save(deps.as_mut().storage, &Addr::unchecked("caller"), &Initiator {
+            allowance: Some(Uint128::new(500)),
+            used: Uint128::new(100),
+            expires_at: None,
+            max_per_request: None,
+        }).unwrap();
+        seed_pending(&mut deps, 100);
+        let oracle = message_info(&Addr::unchecked("oracle"), &[]);
+        let keeper = message_info(&Addr::unchecked("keeper"), &[]);
+        let admin = message_info(&Addr::unchecked("admin"), &[]);
+        let timeout = ExecuteMsg::OnPayoutTimeout { request_id: 3 };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            admin.clone(),
+            ExecuteMsg::UpdateRetryPolicy {
+                retry_policy: Some(RetryPolicy { max_attempts: 0, backoff: Duration::Blocks(2) }),
+            },
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidRetryPolicy {}));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            admin,
+            ExecuteMsg::UpdateRetryPolicy {
+                retry_policy: Some(RetryPolicy { max_attempts: 2, backoff: Duration::Blocks(2) }),
+            },
+        ).unwrap();
+
+        // the payout waits for its backoff and the crank sends the retry
+        let resp = execute(deps.as_mut(), env.clone(), oracle.clone(), timeout.clone()).unwrap();
+        assert!(resp.messages.is_empty());
+        let err = execute(deps.as_mut(), env.clone(), keeper.clone(), ExecuteMsg::Crank {}).unwrap_err();
+        assert!(matches!(err, ContractError::NothingDue {}));
+
+        env.block.height += 2;
+        let resp = execute(deps.as_mut(), env.clone(), keeper.clone(), ExecuteMsg::Crank {}).unwrap();
+        assert_eq!(resp.messages.len(), 1);
+        let info: PendingPayoutInfo = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::PendingPayout { id: 3 }).unwrap(),
+        ).unwrap();
+        assert_eq!((info.attempts, info.retry_at), (2, None));
+
+        // out of attempts: the payout is closed and its allowance released
+        let resp = execute(deps.as_mut(), env.clone(), oracle, timeout).unwrap();
+        assert!(resp.attributes.contains(&cosmwasm_std::attr("status", "timed_out")));
+        assert!(PENDING_PAYOUTS.is_empty(&deps.storage));
+        assert_eq!(BUDGET_USAGE.load(&deps.storage).unwrap().quote_reserved, Uint128::zero());
+        assert_eq!(INITIATORS.load(&deps.storage, &Addr::unchecked("caller")).unwrap().used, Uint128::zero());
+        let record = crate::state::payouts().load(&deps.storage, 3).unwrap();
+        assert_eq!(record.status, PayoutStatus::TimedOut);
+        assert_eq!(record.reason.as_deref(), Some("price request timed out after 2 attempts"));
+    }
+
+    #[test]
+    fn pending_payouts_are_cancelled_by_their_requester_or_the_admin() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        seed_config(&mut deps);
+        seed_pending(&mut deps, 100);
+        let cancel = |seq| ExecuteMsg::CancelPendingPayout { expected_seq: seq };
+
+        let stranger = message_info(&Addr::unchecked("stranger"), &[]);
+        let err = execute(deps.as_mut(), env.clone(), stranger.clone(), cancel(Some(3))).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = execute(deps.as_mut(), env.clone(), stranger, cancel(None)).unwrap_err();
+        assert!(matches!(err, ContractError::NoPendingPayout {}));
+
+        execute(deps.as_mut(), env.clone(), message_info(&Addr::unchecked("caller"), &[]), cancel(None)).unwrap();
+        assert!(PENDING_PAYOUTS.is_empty(&deps.storage));
+        assert_eq!(crate::state::payouts().load(&deps.storage, 3).unwrap().status, PayoutStatus::Cancelled);
+
+        seed_pending(&mut deps, 100);
+        let admin = message_info(&Addr::unchecked("admin"), &[]);
+        execute(deps.as_mut(), env.clone(), admin.clone(), cancel(Some(3))).unwrap();
+        let err = execute(deps.as_mut(), env, admin, cancel(Some(3))).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownPayoutRequest { request_id: 3 }));
+    }
 }
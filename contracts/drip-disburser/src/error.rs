@@ -1,4 +1,6 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
+use cw_utils::PaymentError;
+use drip_disburser_interface::msg::LifecycleStatus;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,6 +8,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
     #[error("Unauthorized")]
     Unauthorized {},
     // Add any other custom errors you like here.
@@ -21,6 +26,50 @@ pub enum ContractError {
     InvalidAmount {},
     #[error("Invalid payout duration: {reason}")]
     InvalidDuration { reason: String },
-    #[error("Pending payout already exists; set replace_pending=true to overwrite")]
-    PendingAlreadyExists {},
+    #[error("Too many pending payouts")]
+    TooManyPendingPayouts {},
+    #[error("Unknown payout request {request_id}")]
+    UnknownPayoutRequest { request_id: u64 },
+    #[error("No pending payout to cancel")]
+    NoPendingPayout {},
+    #[error("Invalid retry policy: at least one attempt required")]
+    InvalidRetryPolicy {},
+    #[error("Invalid price band: min must not exceed max")]
+    InvalidPriceBand {},
+    #[error("Invalid decimals: at most 18 supported (got {decimals})")]
+    InvalidDecimals { decimals: u32 },
+    #[error("Deposit does not match the settlement asset")]
+    InvalidSettlementAsset {},
+    #[error("No settlement tokens to pay out")]
+    NoSettlementBalance {},
+    #[error("Invalid schedule: non-zero amount and interval required, start and end in the unit of the interval")]
+    InvalidSchedule {},
+    #[error("Too many schedules")]
+    TooManySchedules {},
+    #[error("Too many funders")]
+    TooManyFunders {},
+    #[error("Unknown schedule {id}")]
+    UnknownSchedule { id: u64 },
+    #[error("Invalid keeper bounty: non-zero reward and cap required, in a denom other than the settlement asset")]
+    InvalidKeeperBounty {},
+    #[error("No scheduled payout is due")]
+    NothingDue {},
+    #[error("Payout budget exhausted")]
+    BudgetExhausted {},
+    #[error("Requested {requested} exceeds the remaining quote budget of {remaining}")]
+    QuoteBudgetExceeded { requested: Uint128, remaining: Uint128 },
+    #[error("Not allowed while the disburser is {status:?}")]
+    InvalidStatus { status: LifecycleStatus },
+    #[error("Unknown payment initiator")]
+    UnknownInitiator {},
+    #[error("Payment initiator has expired")]
+    InitiatorExpired {},
+    #[error("Requested {requested} exceeds the initiator limit of {limit}")]
+    InitiatorLimitExceeded { requested: Uint128, limit: Uint128 },
+    #[error("Recipients must be unique with non-zero weights adding up to 10000 bps")]
+    InvalidRecipients {},
+    #[error("Funding has expired")]
+    FundingExpired {},
+    #[error("Funding has not expired yet")]
+    FundingNotExpired {},
 }
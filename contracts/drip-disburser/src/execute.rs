@@ -0,0 +1,408 @@
+use cosmwasm_std::{
+    coin, ensure, from_json, Addr, BankMsg, Coin, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    Uint128,
+};
+use cw20::Cw20ReceiveMsg;
+use cw_utils::{must_pay, Expiration};
+use drip_disburser_interface::msg::{
+    KeeperBounty, LifecycleStatus, PayoutStatus, PriceBand, ReceiveMsg, RecipientWeight,
+    RetryPolicy,
+};
+
+use crate::asset::Asset;
+use crate::auth::ensure_admin;
+use crate::bounty::{keeper_reward, validate_keeper_bounty};
+use crate::error::ContractError;
+use crate::helpers::{validate_price_band, validate_recipients, validate_retry_policy};
+use crate::state::{
+    close_pending, ensure_active, ensure_open, Config, Initiator, CONFIG, FUNDERS, INITIATORS, MAX_FUNDERS,
+    PENDING_PAYOUTS, STATUS, TOTAL_FUNDED,
+};
+
+/// Accepts a native settlement token deposit and credits it to the sender.
+pub fn fund(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let denom = cfg
+        .settlement_asset
+        .native_denom()
+        .ok_or(ContractError::InvalidSettlementAsset {})?;
+    let amount = must_pay(&info, denom)?;
+    credit_funder(deps, &env, &cfg, info.sender, amount)
+}
+
+/// Accepts a CW20 settlement token deposit and credits it to the sending account.
+pub fn receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure!(
+        matches!(&cfg.settlement_asset, Asset::Cw20 { contract_addr } if *contract_addr == info.sender),
+        ContractError::InvalidSettlementAsset {}
+    );
+    ensure!(!wrapper.amount.is_zero(), ContractError::InvalidAmount {});
+
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::Fund {} => {
+            let funder = deps.api.addr_validate(&wrapper.sender)?;
+            credit_funder(deps, &env, &cfg, funder, wrapper.amount)
+        }
+    }
+}
+
+pub fn credit_funder(
+    deps: DepsMut,
+    env: &Env,
+    cfg: &Config,
+    funder: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    ensure_active(deps.storage)?;
+    ensure!(
+        !cfg.funding_expiration.is_expired(&env.block),
+        ContractError::FundingExpired {}
+    );
+
+    ensure!(
+        FUNDERS.has(deps.storage, &funder)
+            || FUNDERS.keys(deps.storage, None, None, Order::Ascending).count() < MAX_FUNDERS,
+        ContractError::TooManyFunders {}
+    );
+
+    let deposited = FUNDERS.update(deps.storage, &funder, |d| -> StdResult<_> {
+        Ok(d.unwrap_or_default() + amount)
+    })?;
+    TOTAL_FUNDED.update(deps.storage, |total| -> StdResult<_> { Ok(total + amount) })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "fund")
+        .add_attribute("funder", funder)
+        .add_attribute("amount", amount)
+        .add_attribute("deposited", deposited))
+}
+
+/// Ends the grant: cancels the pending payouts and returns the remaining
+/// settlement and quote balances to the refund address.
+pub fn terminate(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+
+    ensure_open(deps.storage)?;
+    STATUS.save(deps.storage, &LifecycleStatus::Terminated)?;
+
+    Ok(close_out(deps, &env, &cfg, None)?.add_attribute("action", "terminate"))
+}
+
+/// Closes out a disburser whose `funding_expiration` has passed. Callable by anyone,
+/// for one keeper bounty unit.
+pub fn sweep(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure!(
+        cfg.funding_expiration.is_expired(&env.block),
+        ContractError::FundingNotExpired {}
+    );
+
+    ensure_open(deps.storage)?;
+    STATUS.save(deps.storage, &LifecycleStatus::Expired)?;
+
+    // paid before the close-out so that the refund leaves the reward in place
+    let reward = keeper_reward(deps.storage, &deps.querier, &env, cfg.keeper_bounty.as_ref(), 1)?;
+    let mut resp = close_out(deps, &env, &cfg, reward.clone())?.add_attribute("action", "sweep");
+    if let Some(reward) = reward {
+        resp = resp
+            .add_attribute("keeper_reward", reward.to_string())
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![reward],
+            });
+    }
+    Ok(resp)
+}
+
+/// Cancels the pending payout `expected_seq`, which the caller must have requested unless
+/// it is the admin, or every pending payout the caller requested.
+pub fn cancel_pending_payout(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    expected_seq: Option<u64>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+
+    let seqs = match expected_seq {
+        Some(seq) => {
+            let pending = PENDING_PAYOUTS
+                .may_load(deps.storage, seq)?
+                .ok_or(ContractError::UnknownPayoutRequest { request_id: seq })?;
+            ensure!(
+                pending.requester == info.sender || cfg.admin == info.sender,
+                ContractError::Unauthorized {}
+            );
+            vec![seq]
+        }
+        None => PENDING_PAYOUTS
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter(|item| !matches!(item, Ok((_, p)) if p.requester != info.sender))
+            .map(|item| item.map(|(seq, _)| seq))
+            .collect::<StdResult<Vec<_>>>()?,
+    };
+    ensure!(!seqs.is_empty(), ContractError::NoPendingPayout {});
+
+    let mut resp = Response::new().add_attribute("action", "cancel_pending_payout");
+    for seq in seqs {
+        close_pending(deps.storage, &env.block, seq, PayoutStatus::Cancelled)?;
+        resp = resp.add_attribute("cancelled_request_id", seq.to_string());
+    }
+    Ok(resp)
+}
+
+pub fn update_recipients(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipients: Vec<RecipientWeight>,
+    dust_recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    cfg.recipients = validate_recipients(deps.api, &cfg.settlement_asset, recipients)?;
+    if let Some(addr) = dust_recipient {
+        cfg.recipient_addr = deps.api.addr_validate(&addr)?;
+    }
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_recipients")
+        .add_attribute("recipients", cfg.recipients.len().to_string())
+        .add_attribute("dust_recipient", cfg.recipient_addr))
+}
+
+pub fn add_payment_initiator(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+    allowance: Option<Uint128>,
+    expires_at: Option<Expiration>,
+    max_per_request: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    let addr = deps.api.addr_validate(&addr)?;
+    // keep the consumed amount when the limits of an existing initiator change
+    let used = INITIATORS
+        .may_load(deps.storage, &addr)?
+        .map(|i| i.used)
+        .unwrap_or_default();
+    INITIATORS.save(
+        deps.storage,
+        &addr,
+        &Initiator {
+            allowance,
+            used,
+            expires_at,
+            max_per_request,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_payment_initiator")
+        .add_attribute("initiator", addr))
+}
+
+pub fn remove_payment_initiator(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    let addr = deps.api.addr_validate(&addr)?;
+    ensure!(
+        INITIATORS.has(deps.storage, &addr),
+        ContractError::UnknownInitiator {}
+    );
+    INITIATORS.remove(deps.storage, &addr);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_payment_initiator")
+        .add_attribute("initiator", addr))
+}
+
+pub fn update_price_band(
+    deps: DepsMut,
+    info: MessageInfo,
+    price_band: Option<PriceBand>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    if let Some(band) = &price_band {
+        validate_price_band(band)?;
+    }
+    cfg.price_band = price_band;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new().add_attribute("action", "update_price_band"))
+}
+
+pub fn update_cached_price_age(
+    deps: DepsMut,
+    info: MessageInfo,
+    cached_price_age: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    cfg.cached_price_age = cached_price_age;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new().add_attribute("action", "update_cached_price_age"))
+}
+
+pub fn update_keeper_bounty(
+    deps: DepsMut,
+    info: MessageInfo,
+    keeper_bounty: Option<KeeperBounty>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    if let Some(bounty) = &keeper_bounty {
+        validate_keeper_bounty(&cfg.settlement_asset, bounty)?;
+    }
+    cfg.keeper_bounty = keeper_bounty;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new().add_attribute("action", "update_keeper_bounty"))
+}
+
+pub fn update_retry_policy(
+    deps: DepsMut,
+    info: MessageInfo,
+    retry_policy: Option<RetryPolicy>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    if let Some(policy) = &retry_policy {
+        validate_retry_policy(policy)?;
+    }
+    cfg.retry_policy = retry_policy;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new().add_attribute("action", "update_retry_policy"))
+}
+
+pub fn pause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    transition(deps, info, LifecycleStatus::Active, LifecycleStatus::Paused)?;
+    Ok(Response::new().add_attribute("action", "pause"))
+}
+
+pub fn resume(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    transition(deps, info, LifecycleStatus::Paused, LifecycleStatus::Active)?;
+    Ok(Response::new().add_attribute("action", "resume"))
+}
+
+fn transition(
+    deps: DepsMut,
+    info: MessageInfo,
+    from: LifecycleStatus,
+    to: LifecycleStatus,
+) -> Result<(), ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+
+    let status = STATUS.load(deps.storage)?;
+    ensure!(status == from, ContractError::InvalidStatus { status });
+    STATUS.save(deps.storage, &to)?;
+    Ok(())
+}
+
+/// Cancels every pending payout and refunds every remaining settlement, quote and keeper
+/// bounty token, less the `reserved` coin already promised in the same transaction.
+pub(crate) fn close_out(
+    deps: DepsMut,
+    env: &Env,
+    cfg: &Config,
+    reserved: Option<Coin>,
+) -> Result<Response, ContractError> {
+    let mut resp = Response::new();
+
+    let pending = PENDING_PAYOUTS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for seq in pending {
+        close_pending(deps.storage, &env.block, seq, PayoutStatus::Cancelled)?;
+        resp = resp.add_attribute("cancelled_request_id", seq.to_string());
+    }
+
+    // settlement tokens go back to the funders pro rata to their deposits, never more
+    // than they deposited; rounding dust and anything not deposited through `Fund`
+    // go to the refund address
+    let asset = &cfg.settlement_asset;
+    let balance = asset.query_balance(&deps.querier, &env.contract.address)?;
+    let total_funded = TOTAL_FUNDED.may_load(deps.storage)?.unwrap_or_default();
+    let refundable = balance.min(total_funded);
+
+    let mut leftover = balance;
+    if !total_funded.is_zero() {
+        let funders = FUNDERS
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for (funder, deposited) in funders {
+            let share = refundable.multiply_ratio(deposited, total_funded);
+            if share.is_zero() {
+                continue;
+            }
+            leftover -= share;
+            resp = resp.add_message(asset.transfer_msg(funder, share)?);
+        }
+    }
+
+    // native leftovers share one bank message with the quote refund
+    let mut refund = vec![];
+    if !leftover.is_zero() {
+        match asset.native_denom() {
+            Some(denom) => refund.push(coin(leftover.u128(), denom)),
+            None => resp = resp.add_message(asset.transfer_msg(&cfg.refund_addr, leftover)?),
+        }
+    }
+    let mut denoms = vec![cfg.quote_asset_limit.denom.as_str()];
+    if let Some(bounty) = &cfg.keeper_bounty {
+        denoms.push(&bounty.reward.denom);
+    }
+    denoms.dedup();
+    for denom in denoms {
+        if asset.native_denom() == Some(denom) {
+            continue;
+        }
+        let mut balance = deps.querier.query_balance(&env.contract.address, denom)?;
+        if let Some(reserved) = reserved.as_ref().filter(|r| r.denom == denom) {
+            balance.amount = balance.amount.saturating_sub(reserved.amount);
+        }
+        if !balance.amount.is_zero() {
+            refund.push(balance);
+        }
+    }
+    refund.sort_unstable_by(|a, b| a.denom.cmp(&b.denom));
+
+    if !refund.is_empty() {
+        resp = resp.add_message(BankMsg::Send {
+            to_address: cfg.refund_addr.to_string(),
+            amount: refund,
+        });
+    }
+
+    Ok(resp.add_attribute("refund_addr", cfg.refund_addr.to_string()))
+}
@@ -1,9 +1,15 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{to_json_binary, Addr, CosmosMsg, StdResult, WasmMsg};
+use cosmwasm_std::{
+    ensure, to_json_binary, Addr, Api, CosmosMsg, Decimal, StdResult, Uint128, Uint256, WasmMsg,
+};
 
-use drip_disburser_interface::msg::ExecuteMsg;
+use drip_disburser_interface::msg::{ExecuteMsg, PriceBand, RecipientWeight, RetryPolicy};
+
+use crate::asset::Asset;
+use crate::state::{Config, Recipient, TOTAL_WEIGHT_BPS};
+use crate::ContractError;
 
 /// CwTemplateContract is a wrapper around Addr that provides a lot of helpers
 /// for working with this.
@@ -25,3 +31,87 @@ impl CwTemplateContract {
             .into())
     }
 }
+
+/// Validates weighted recipients: non-empty, unique, non-zero weights adding up to 10000 bps.
+/// Remote recipients need a native settlement asset, as only native tokens go over ICS-20.
+pub fn validate_recipients(
+    api: &dyn Api,
+    asset: &Asset,
+    recipients: Vec<RecipientWeight>,
+) -> Result<Vec<Recipient>, ContractError> {
+    let mut checked: Vec<Recipient> = Vec::with_capacity(recipients.len());
+    let mut total: u32 = 0;
+    for r in recipients {
+        let addr = match &r.channel_id {
+            Some(channel) => {
+                ensure!(
+                    !channel.is_empty() && !r.addr.is_empty() && asset.native_denom().is_some(),
+                    ContractError::InvalidRecipients {}
+                );
+                r.addr
+            }
+            None => api.addr_validate(&r.addr)?.into_string(),
+        };
+        ensure!(
+            r.weight_bps > 0
+                && !checked.iter().any(|c| c.addr == addr && c.channel_id == r.channel_id),
+            ContractError::InvalidRecipients {}
+        );
+        total += u32::from(r.weight_bps);
+        checked.push(Recipient {
+            addr,
+            weight_bps: r.weight_bps,
+            channel_id: r.channel_id,
+        });
+    }
+    ensure!(
+        total == u32::from(TOTAL_WEIGHT_BPS),
+        ContractError::InvalidRecipients {}
+    );
+    Ok(checked)
+}
+
+/// Ensures the band is not inverted.
+pub fn validate_price_band(band: &PriceBand) -> Result<(), ContractError> {
+    if let (Some(min), Some(max)) = (band.min, band.max) {
+        ensure!(min <= max, ContractError::InvalidPriceBand {});
+    }
+    Ok(())
+}
+
+pub fn validate_retry_policy(policy: &RetryPolicy) -> Result<(), ContractError> {
+    ensure!(policy.max_attempts > 0, ContractError::InvalidRetryPolicy {});
+    Ok(())
+}
+
+/// Checks `price` against the request band and the configured global band, bounds inclusive.
+pub fn price_in_band(price: Decimal, bands: &[&PriceBand]) -> bool {
+    bands.iter().all(|band| {
+        band.min.is_none_or(|min| price >= min) && band.max.is_none_or(|max| price <= max)
+    })
+}
+
+/// Settlement base units bought by `amount_in_quote` quote base units, rounded down.
+///
+/// `price` is the oracle price of one whole settlement token in whole quote tokens, so
+/// `settlement = amount_in_quote * 10^settlement_decimals / (price * 10^quote_decimals)`.
+pub fn quote_to_settlement(cfg: &Config, amount_in_quote: Uint128, price: Decimal) -> StdResult<Uint256> {
+    let num = Uint256::from(amount_in_quote)
+        .checked_mul(pow10(cfg.settlement_decimals + Decimal::DECIMAL_PLACES)?)?;
+    let den = Uint256::from(price.atomics()).checked_mul(pow10(cfg.quote_decimals)?)?;
+    Ok(num.checked_div(den)?)
+}
+
+/// Quote base units worth `amount` settlement base units, rounded up.
+pub fn settlement_to_quote(cfg: &Config, amount: Uint128, price: Decimal) -> StdResult<Uint256> {
+    let num = Uint256::from(amount)
+        .checked_mul(Uint256::from(price.atomics()))?
+        .checked_mul(pow10(cfg.quote_decimals)?)?;
+    let den = pow10(cfg.settlement_decimals + Decimal::DECIMAL_PLACES)?;
+    let quote = num.checked_div(den)?;
+    Ok(if (quote * den) < num { quote + Uint256::one() } else { quote })
+}
+
+fn pow10(exp: u32) -> StdResult<Uint256> {
+    Ok(Uint256::from(10u8).checked_pow(exp)?)
+}
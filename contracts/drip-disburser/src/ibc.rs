@@ -0,0 +1,155 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    coin, from_json, to_json_binary, Binary, DepsMut, Env, IbcBasicResponse, IbcPacket,
+    IbcSourceCallbackMsg, IbcSrcCallback, Reply, Response, StdError, StdResult, Storage, SubMsg,
+    TransferMsgBuilder,
+};
+use cosmwasm_schema::cw_serde;
+use drip_disburser_interface::msg::{LifecycleStatus, PayoutStatus};
+use prost::Message;
+
+use crate::error::ContractError;
+use crate::state::{
+    payouts, release_allowance, Config, IbcTransfer, BUDGET_USAGE, CONFIG, IBC_TRANSFERS, STATUS,
+};
+
+/// Reply to an ICS-20 payout, carrying the packet sequence used to match its acknowledgement
+pub const IBC_TRANSFER_REPLY_ID: u64 = 1;
+
+/// See https://github.com/cosmos/ibc-go/blob/main/proto/ibc/applications/transfer/v1/tx.proto
+#[derive(Clone, PartialEq, Message)]
+struct MsgTransferResponse {
+    #[prost(uint64, tag = "1")]
+    sequence: u64,
+}
+
+/// ICS-20 acknowledgement
+#[cw_serde]
+enum Ics20Ack {
+    Result(Binary),
+    Error(String),
+}
+
+/// Builds the ICS-20 transfer of a payout share to a remote recipient, asking for
+/// source callbacks so that a returned transfer can be recorded.
+pub fn transfer_submsg(
+    env: &Env,
+    cfg: &Config,
+    transfer: IbcTransfer,
+    to_address: String,
+) -> Result<SubMsg, ContractError> {
+    let denom = cfg
+        .settlement_asset
+        .native_denom()
+        .ok_or(ContractError::InvalidSettlementAsset {})?;
+    let msg = TransferMsgBuilder::new(
+        transfer.channel_id.clone(),
+        to_address,
+        coin(transfer.amount.u128(), denom),
+        env.block.time.plus_seconds(cfg.ibc_transfer_timeout),
+    )
+    .with_src_callback(IbcSrcCallback {
+        address: env.contract.address.clone(),
+        gas_limit: None,
+    })
+    .build();
+
+    Ok(SubMsg::reply_on_success(msg, IBC_TRANSFER_REPLY_ID).with_payload(to_json_binary(&transfer)?))
+}
+
+/// Indexes a sent ICS-20 payout by its packet sequence.
+pub fn transfer_reply(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let transfer: IbcTransfer = from_json(&msg.payload)?;
+    let response = msg.result.into_result().map_err(StdError::generic_err)?;
+    let sequence = response
+        .msg_responses
+        .first()
+        .map(|r| MsgTransferResponse::decode(r.value.as_slice()))
+        .transpose()
+        .map_err(|e| StdError::generic_err(format!("fail to decode transfer response: {e}")))?
+        .ok_or_else(|| StdError::generic_err("missing transfer response"))?
+        .sequence;
+
+    IBC_TRANSFERS.save(deps.storage, (&transfer.channel_id, sequence), &transfer)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "ibc_transfer")
+        .add_attribute("request_id", transfer.payout_id.to_string())
+        .add_attribute("channel_id", transfer.channel_id)
+        .add_attribute("packet_sequence", sequence.to_string()))
+}
+
+/// Settles the outcome of an ICS-20 payout. A successful acknowledgement only clears the
+/// transfer; an error acknowledgement or a timeout means the tokens came back, so the payout
+/// is marked failed and its budget usage and initiator allowance are given back. Tokens
+/// returned after the disburser was closed out are forwarded to the refund address.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn ibc_source_callback(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcSourceCallbackMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    let (packet, failure) = match msg {
+        IbcSourceCallbackMsg::Acknowledgement(ack) => {
+            let failure = match from_json(&ack.acknowledgement.data) {
+                Ok(Ics20Ack::Result(_)) => None,
+                Ok(Ics20Ack::Error(error)) => Some(error),
+                Err(_) => Some("unreadable acknowledgement".to_string()),
+            };
+            (ack.original_packet, failure)
+        }
+        IbcSourceCallbackMsg::Timeout(timeout) => (timeout.packet, Some("ibc transfer timed out".to_string())),
+    };
+
+    let resp = IbcBasicResponse::new()
+        .add_attribute("action", "ibc_source_callback")
+        .add_attribute("packet_sequence", packet.sequence.to_string());
+    let Some(transfer) = take_transfer(deps.storage, &packet)? else {
+        return Ok(resp.add_attribute("status", "unknown"));
+    };
+    let Some(reason) = failure else {
+        return Ok(resp.add_attribute("status", "delivered"));
+    };
+
+    payouts().update(deps.storage, transfer.payout_id, |record| -> StdResult<_> {
+        let mut record = record.ok_or_else(|| StdError::not_found("payout"))?;
+        record.status = PayoutStatus::Failed;
+        record.reason = Some(reason);
+        record.returned += transfer.amount;
+        Ok(record)
+    })?;
+    BUDGET_USAGE.update(deps.storage, |mut usage| -> StdResult<_> {
+        usage.quote = usage.quote.saturating_sub(transfer.quote_amount);
+        usage.settlement = usage.settlement.saturating_sub(transfer.amount);
+        Ok(usage)
+    })?;
+    release_allowance(deps.storage, &transfer.requester, transfer.quote_amount)?;
+
+    let mut resp = resp
+        .add_attribute("status", "returned")
+        .add_attribute("request_id", transfer.payout_id.to_string())
+        .add_attribute("returned", transfer.amount);
+    if matches!(
+        STATUS.load(deps.storage)?,
+        LifecycleStatus::Terminated | LifecycleStatus::Expired
+    ) {
+        let cfg = CONFIG.load(deps.storage)?;
+        resp = resp
+            .add_message(cfg.settlement_asset.transfer_msg(&cfg.refund_addr, transfer.amount)?)
+            .add_attribute("refund_addr", cfg.refund_addr);
+    }
+    Ok(resp)
+}
+
+fn take_transfer(
+    storage: &mut dyn Storage,
+    packet: &IbcPacket,
+) -> StdResult<Option<IbcTransfer>> {
+    let key = (packet.src.channel_id.as_str(), packet.sequence);
+    let transfer = IBC_TRANSFERS.may_load(storage, key)?;
+    if transfer.is_some() {
+        IBC_TRANSFERS.remove(storage, key);
+    }
+    Ok(transfer)
+}
@@ -1,6 +1,15 @@
+pub mod asset;
+mod auth;
+pub mod bounty;
+pub mod callbacks;
 pub mod contract;
 mod error;
+pub mod execute;
 pub mod helpers;
+pub mod ibc;
+pub mod migrate;
+pub mod query;
+pub mod schedule;
 pub mod state;
 
 pub use crate::error::ContractError;
@@ -0,0 +1,136 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, DepsMut, Env, Response, Storage, Uint128};
+use cw2::ensure_from_older_version;
+use cw_storage_plus::Item;
+use cw_utils::Expiration;
+use drip_disburser_interface::msg::{DurationBounds, DurationLimit, LifecycleStatus, MigrateMsg};
+
+use crate::asset::Asset;
+use crate::contract::{CONTRACT_NAME, CONTRACT_VERSION};
+use crate::error::ContractError;
+use crate::state::{
+    BudgetUsage, Config, Initiator, Recipient, BUDGET_USAGE, CONFIG,
+    DEFAULT_IBC_TRANSFER_TIMEOUT, INITIATORS, STATUS, TOTAL_FUNDED, TOTAL_WEIGHT_BPS,
+};
+
+/// Config of the early deployments, with the initiators inline and either the
+/// seconds-only `withdrawal_ttl` or the later `payout_duration_bounds`
+#[cw_serde]
+struct LegacyConfig {
+    settlement_asset_limit: Coin,
+    quote_asset_limit: Coin,
+    admin: Addr,
+    recipient_addr: Addr,
+    price_feeder_addr: Addr,
+    payment_initiator_addrs: Vec<Addr>,
+    funding_expiration: Expiration,
+    withdrawal_ttl: Option<WithdrawalTtl>,
+    payout_duration_bounds: Option<DurationBounds>,
+}
+
+#[cw_serde]
+struct WithdrawalTtl {
+    default_sec: u64,
+    max_sec: u64,
+}
+
+impl From<WithdrawalTtl> for DurationBounds {
+    /// The legacy TTL only knew seconds, so block-based durations stay disallowed.
+    fn from(ttl: WithdrawalTtl) -> Self {
+        DurationBounds {
+            default: DurationLimit {
+                blocks: 0,
+                seconds: ttl.default_sec,
+            },
+            max: Some(DurationLimit {
+                blocks: 0,
+                seconds: ttl.max_sec,
+            }),
+            min: None,
+        }
+    }
+}
+
+const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+/// Storage key of the single pending payout of the legacy layout
+const LEGACY_PENDING_PAYOUT_KEY: &[u8] = b"pending_payout";
+
+/// Upgrades a deployment in place. Only the same contract name is accepted and the
+/// stored version must not be newer than this code.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let from_version = ensure_from_older_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let resp = Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", from_version.to_string())
+        .add_attribute("to_version", CONTRACT_VERSION);
+
+    if CONFIG.load(deps.storage).is_ok() {
+        return Ok(resp.add_attribute("layout", "current"));
+    }
+    let legacy = LEGACY_CONFIG.load(deps.storage)?;
+    migrate_legacy(deps.storage, legacy)?;
+
+    Ok(resp.add_attribute("layout", "legacy"))
+}
+
+fn migrate_legacy(storage: &mut dyn Storage, legacy: LegacyConfig) -> Result<(), ContractError> {
+    let payout_duration_bounds = match (legacy.payout_duration_bounds, legacy.withdrawal_ttl) {
+        (Some(bounds), _) => bounds,
+        (None, Some(ttl)) => ttl.into(),
+        (None, None) => {
+            return Err(ContractError::InvalidDuration {
+                reason: "legacy config has no payout duration".to_string(),
+            })
+        }
+    };
+
+    let cfg = Config {
+        settlement_asset: Asset::Native {
+            denom: legacy.settlement_asset_limit.denom,
+        },
+        settlement_asset_limit: legacy.settlement_asset_limit.amount,
+        quote_asset_limit: legacy.quote_asset_limit,
+        refund_addr: legacy.admin.clone(),
+        admin: legacy.admin,
+        recipients: vec![Recipient {
+            addr: legacy.recipient_addr.to_string(),
+            weight_bps: TOTAL_WEIGHT_BPS,
+            channel_id: None,
+        }],
+        recipient_addr: legacy.recipient_addr,
+        price_feeder_addr: legacy.price_feeder_addr,
+        funding_expiration: legacy.funding_expiration,
+        payout_duration_bounds,
+        price_band: None,
+        max_price_age: None,
+        cached_price_age: None,
+        quote_decimals: 0,
+        settlement_decimals: 0,
+        ibc_transfer_timeout: DEFAULT_IBC_TRANSFER_TIMEOUT,
+        keeper_bounty: None,
+        retry_policy: None,
+    };
+    CONFIG.save(storage, &cfg)?;
+
+    for addr in legacy.payment_initiator_addrs {
+        INITIATORS.save(storage, &addr, &Initiator::unlimited())?;
+    }
+    if !STATUS.exists(storage) {
+        STATUS.save(storage, &LifecycleStatus::Active)?;
+    }
+    if !BUDGET_USAGE.exists(storage) {
+        BUDGET_USAGE.save(storage, &BudgetUsage::default())?;
+    }
+    if !TOTAL_FUNDED.exists(storage) {
+        TOTAL_FUNDED.save(storage, &Uint128::zero())?;
+    }
+    // the legacy pending payout has no requester to account it to; its callback is
+    // rejected as unknown and the initiator can simply request again
+    storage.remove(LEGACY_PENDING_PAYOUT_KEY);
+
+    Ok(())
+}
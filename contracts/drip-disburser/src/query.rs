@@ -0,0 +1,276 @@
+use cosmwasm_std::{ensure, Decimal, Deps, Env, Order, StdError, StdResult, Timestamp, Uint128};
+use cw_storage_plus::Bound;
+use drip_disburser_interface::msg::{
+    AvailableBalanceResponse, BudgetResponse, ConfigResponse, FunderInfo, FundersResponse,
+    InitiatorInfo, InitiatorsResponse, PayoutRecord, PayoutStatus, PayoutsResponse,
+    PendingPayoutInfo, PendingPayoutsResponse, RecipientWeight, ScheduleInfo, SchedulesResponse,
+    SimulatePayoutResponse,
+    StatusResponse,
+};
+
+use crate::callbacks::{settlement_amounts, split_payout};
+use crate::state::{
+    payouts as payout_records, status_code, PendingPayout, BUDGET_USAGE, CONFIG, FUNDERS,
+    INITIATORS, PENDING_PAYOUTS, SCHEDULES, STATUS, TOTAL_FUNDED,
+};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        settlement_asset: cfg.settlement_asset.into(),
+        settlement_asset_limit: cfg.settlement_asset_limit,
+        quote_asset_limit: cfg.quote_asset_limit,
+        admin: cfg.admin,
+        refund_addr: cfg.refund_addr,
+        recipient_addr: cfg.recipient_addr,
+        recipients: cfg
+            .recipients
+            .into_iter()
+            .map(|r| RecipientWeight {
+                addr: r.addr,
+                weight_bps: r.weight_bps,
+                channel_id: r.channel_id,
+            })
+            .collect(),
+        oracle_addr: cfg.price_feeder_addr,
+        funding_expiration: cfg.funding_expiration,
+        payout_duration_bounds: cfg.payout_duration_bounds,
+        price_band: cfg.price_band,
+        max_price_age: cfg.max_price_age,
+        cached_price_age: cfg.cached_price_age,
+        quote_decimals: cfg.quote_decimals,
+        settlement_decimals: cfg.settlement_decimals,
+        ibc_transfer_timeout: cfg.ibc_transfer_timeout,
+        keeper_bounty: cfg.keeper_bounty,
+        retry_policy: cfg.retry_policy,
+    })
+}
+
+pub fn status(deps: Deps) -> StdResult<StatusResponse> {
+    Ok(StatusResponse {
+        status: STATUS.load(deps.storage)?,
+    })
+}
+
+pub fn budget(deps: Deps) -> StdResult<BudgetResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let usage = BUDGET_USAGE.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(BudgetResponse {
+        quote_used: usage.quote,
+        quote_reserved: usage.quote_reserved,
+        quote_remaining: usage.quote_remaining(&cfg),
+        settlement_used: usage.settlement,
+        settlement_remaining: usage.settlement_remaining(&cfg),
+        quote_limit: cfg.quote_asset_limit,
+        settlement_asset: cfg.settlement_asset.into(),
+        settlement_limit: cfg.settlement_asset_limit,
+    })
+}
+
+pub fn available_balance(deps: Deps, env: Env) -> StdResult<AvailableBalanceResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let usage = BUDGET_USAGE.may_load(deps.storage)?.unwrap_or_default();
+    let balance = cfg
+        .settlement_asset
+        .query_balance(&deps.querier, &env.contract.address)?;
+
+    Ok(AvailableBalanceResponse {
+        settlement_balance: balance,
+        settlement_available: balance.min(usage.settlement_remaining(&cfg)),
+        quote_available: usage.quote_available(&cfg),
+    })
+}
+
+pub fn pending_payout(deps: Deps, id: u64) -> StdResult<PendingPayoutInfo> {
+    PENDING_PAYOUTS.load(deps.storage, id).map(pending_info)
+}
+
+pub fn pending_payouts(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingPayoutsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let payouts = PENDING_PAYOUTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, pending)| pending_info(pending)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(PendingPayoutsResponse { payouts })
+}
+
+fn pending_info(pending: PendingPayout) -> PendingPayoutInfo {
+    PendingPayoutInfo {
+        id: pending.seq,
+        requester: pending.requester,
+        amount_in_quote: pending.amount_in_quote,
+        expires_at: pending.expires_at,
+        price_band: pending.price_band,
+        created_at: pending.created_at,
+        attempts: pending.attempts,
+        retry_at: pending.retry_at,
+    }
+}
+
+pub fn schedules(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SchedulesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let schedules = SCHEDULES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(id, state)| ScheduleInfo {
+                id,
+                schedule: state.schedule,
+                next_due: state.next_due,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(SchedulesResponse { schedules })
+}
+
+pub fn initiators(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<InitiatorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .map(|a| deps.api.addr_validate(&a))
+        .transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let initiators = INITIATORS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(addr, i)| InitiatorInfo {
+                addr,
+                allowance: i.allowance,
+                used: i.used,
+                expires_at: i.expires_at,
+                max_per_request: i.max_per_request,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(InitiatorsResponse { initiators })
+}
+
+/// Runs the settlement math of `OnPayoutResponse` without the band and staleness checks.
+pub fn simulate_payout(
+    deps: Deps,
+    env: Env,
+    amount_in_quote: Option<Uint128>,
+    price: Decimal,
+) -> StdResult<SimulatePayoutResponse> {
+    ensure!(!price.is_zero(), StdError::generic_err("price must be > 0"));
+    let cfg = CONFIG.load(deps.storage)?;
+    let usage = BUDGET_USAGE.may_load(deps.storage)?.unwrap_or_default();
+    let balance = cfg
+        .settlement_asset
+        .query_balance(&deps.querier, &env.contract.address)?;
+
+    let amount_in_quote = amount_in_quote.unwrap_or_else(|| usage.quote_available(&cfg));
+    let (settlement_amount, quote_paid) =
+        settlement_amounts(&cfg, &usage, balance, amount_in_quote, price)
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+    Ok(SimulatePayoutResponse {
+        amount_in_quote,
+        settlement_amount,
+        quote_paid,
+        recipients: split_payout(&cfg, settlement_amount),
+    })
+}
+
+pub fn funders(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FundersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .map(|a| deps.api.addr_validate(&a))
+        .transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let funders = FUNDERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(addr, deposited)| FunderInfo { addr, deposited }))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(FundersResponse {
+        funders,
+        total_deposited: TOTAL_FUNDED.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
+pub fn payout(deps: Deps, id: u64) -> StdResult<PayoutRecord> {
+    payout_records().load(deps.storage, id)
+}
+
+/// Without a status or time filter, pages walk the ledger by id. With one, they walk the
+/// matching index in `(time, id)` order, so that a selective filter never scans the rest
+/// of the history.
+pub fn payouts(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    status: Option<PayoutStatus>,
+    since: Option<Timestamp>,
+    until: Option<Timestamp>,
+) -> StdResult<PayoutsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let ledger = payout_records();
+
+    if status.is_none() && since.is_none() && until.is_none() {
+        let payouts = ledger
+            .range(deps.storage, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, record)| record))
+            .collect::<StdResult<Vec<_>>>()?;
+        return Ok(PayoutsResponse { payouts });
+    }
+
+    // ids start at 1, so `(t, 0)` sorts before every record at time `t`
+    let since = (since.map_or(0, |t| t.nanos()), 0);
+    let after = start_after
+        .map(|id| ledger.load(deps.storage, id).map(|r| (r.time.nanos(), id)))
+        .transpose()?;
+    let min = match after {
+        Some(after) if after >= since => Bound::exclusive(after),
+        _ => Bound::inclusive(since),
+    };
+    let max = until.map(|t| Bound::exclusive((t.nanos(), 0)));
+
+    let records = match &status {
+        Some(status) => ledger.idx.status.sub_prefix(status_code(status)).range(
+            deps.storage,
+            Some(min),
+            max,
+            Order::Ascending,
+        ),
+        None => ledger.idx.time.range(deps.storage, Some(min), max, Order::Ascending),
+    };
+    let payouts = records
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(PayoutsResponse { payouts })
+}
@@ -0,0 +1,232 @@
+use cosmwasm_std::{ensure, BankMsg, BlockInfo, DepsMut, Env, MessageInfo, Order, Response, StdResult, Uint128};
+use cw_utils::Scheduled;
+use drip_disburser_interface::msg::{Duration, MissedPeriods, PriceBand, Schedule};
+
+use crate::auth::ensure_admin;
+use crate::bounty::keeper_reward;
+use crate::callbacks::{close_timed_out, retry_price_request};
+use crate::contract::{normalize_duration_to_expiration, queue_payout};
+use crate::error::ContractError;
+use crate::state::{
+    ensure_active, ensure_open, PendingPayout, ScheduleState, BUDGET_USAGE, CONFIG, MAX_PENDING_PAYOUTS,
+    MAX_SCHEDULES, PENDING_PAYOUTS, SCHEDULES, SCHEDULE_SEQ,
+};
+
+pub fn add_schedule(
+    deps: DepsMut,
+    info: MessageInfo,
+    schedule: Schedule,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    let start = position(&schedule.interval, &schedule.start)?;
+    if let Some(end) = &schedule.end {
+        ensure!(
+            position(&schedule.interval, end)? > start,
+            ContractError::InvalidSchedule {}
+        );
+    }
+    ensure!(
+        !schedule.amount_in_quote.is_zero() && interval_len(&schedule.interval) > 0,
+        ContractError::InvalidSchedule {}
+    );
+    ensure!(
+        SCHEDULES.keys(deps.storage, None, None, Order::Ascending).count() < MAX_SCHEDULES,
+        ContractError::TooManySchedules {}
+    );
+
+    let id = SCHEDULE_SEQ.may_load(deps.storage)?.unwrap_or_default() + 1;
+    SCHEDULE_SEQ.save(deps.storage, &id)?;
+    SCHEDULES.save(
+        deps.storage,
+        id,
+        &ScheduleState {
+            schedule,
+            next_due: start,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_schedule")
+        .add_attribute("schedule_id", id.to_string()))
+}
+
+pub fn remove_schedule(deps: DepsMut, info: MessageInfo, id: u64) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+    ensure_open(deps.storage)?;
+
+    ensure!(
+        SCHEDULES.has(deps.storage, id),
+        ContractError::UnknownSchedule { id }
+    );
+    SCHEDULES.remove(deps.storage, id);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_schedule")
+        .add_attribute("schedule_id", id.to_string()))
+}
+
+/// Requests the payout of every due schedule and resends the price requests of
+/// timed-out payouts whose backoff has passed. Callable by anyone.
+///
+/// The periods due since the last crank are paid together or all but one are dropped,
+/// depending on `missed_periods`; the amount is trimmed to the unreserved quote budget.
+/// Scheduled payouts are requested by the contract itself with the default duration and
+/// no price band of their own. A retry due after its payout expired closes the payout
+/// as timed out instead. Fails when nothing is due.
+///
+/// The caller earns one keeper bounty unit per price request it sends, so advancing a
+/// schedule without budget left or closing an expired retry goes unrewarded.
+pub fn crank(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    ensure_active(deps.storage)?;
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure!(
+        !cfg.funding_expiration.is_expired(&env.block),
+        ContractError::FundingExpired {}
+    );
+
+    let schedules = SCHEDULES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut resp = Response::new().add_attribute("action", "crank");
+    let mut progressed = 0u32;
+    let mut requested = 0u32;
+
+    let retries = PENDING_PAYOUTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, p)) => p.retry_at.is_some_and(|at| at.is_triggered(&env.block)),
+            Err(_) => true,
+        })
+        .map(|item| item.map(|(_, pending)| pending))
+        .collect::<StdResult<Vec<_>>>()?;
+    for pending in retries {
+        progressed += 1;
+        let seq = pending.seq;
+        if pending.expires_at.is_expired(&env.block) {
+            close_timed_out(deps.storage, &env.block, seq)?;
+            resp = resp.add_attribute("timed_out_request_id", seq.to_string());
+            continue;
+        }
+        let msg = retry_price_request(deps.storage, &cfg, pending)?;
+        requested += 1;
+        resp = resp
+            .add_message(msg)
+            .add_attribute("retried_request_id", seq.to_string());
+    }
+    for (id, mut state) in schedules {
+        let Some(periods) = due_periods(&state, &env.block) else {
+            continue;
+        };
+        if PENDING_PAYOUTS.keys(deps.storage, None, None, Order::Ascending).count() >= MAX_PENDING_PAYOUTS {
+            break;
+        }
+        progressed += 1;
+
+        let len = interval_len(&state.schedule.interval);
+        state.next_due = state.next_due.saturating_add(periods.saturating_mul(len));
+        let end = state
+            .schedule
+            .end
+            .as_ref()
+            .map(|e| position(&state.schedule.interval, e))
+            .transpose()?;
+        if end.is_some_and(|end| state.next_due >= end) {
+            SCHEDULES.remove(deps.storage, id);
+            resp = resp.add_attribute("finished_schedule_id", id.to_string());
+        } else {
+            SCHEDULES.save(deps.storage, id, &state)?;
+        }
+        if periods == 0 {
+            continue;
+        }
+
+        let paid_periods = match state.schedule.missed_periods {
+            MissedPeriods::Accumulate => periods,
+            MissedPeriods::Skip => 1,
+        };
+        let usage = BUDGET_USAGE.load(deps.storage)?;
+        let amount = state
+            .schedule
+            .amount_in_quote
+            .saturating_mul(Uint128::from(paid_periods))
+            .min(usage.quote_available(&cfg));
+        if amount.is_zero() || usage.settlement_remaining(&cfg).is_zero() {
+            resp = resp.add_attribute("exhausted_schedule_id", id.to_string());
+            continue;
+        }
+
+        let expires_at = normalize_duration_to_expiration(&env, &cfg.payout_duration_bounds, None)
+            .map_err(|e| ContractError::InvalidDuration { reason: e.to_string() })?;
+        let pending = PendingPayout {
+            seq: 0,
+            requester: env.contract.address.clone(),
+            amount_in_quote: amount,
+            expires_at,
+            price_band: PriceBand::default(),
+            created_at: env.block.time,
+            attempts: 1,
+            retry_at: None,
+        };
+        let (seq, msg) = queue_payout(deps.storage, &cfg, pending)?;
+        requested += 1;
+        resp = resp
+            .add_message(msg)
+            .add_attribute("schedule_id", id.to_string())
+            .add_attribute("request_id", seq.to_string())
+            .add_attribute("amount_in_quote", amount);
+    }
+    ensure!(progressed > 0, ContractError::NothingDue {});
+
+    if let Some(reward) = keeper_reward(deps.storage, &deps.querier, &env, cfg.keeper_bounty.as_ref(), requested)? {
+        resp = resp
+            .add_attribute("keeper_reward", reward.to_string())
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![reward],
+            });
+    }
+
+    Ok(resp)
+}
+
+/// Number of periods due at `block`, `None` when the next one is not due yet.
+/// `Some(0)` means the schedule ended without another period to pay.
+fn due_periods(state: &ScheduleState, block: &BlockInfo) -> Option<u64> {
+    let now = match state.schedule.interval {
+        Duration::Blocks(_) => block.height,
+        Duration::Seconds(_) => block.time.seconds(),
+    };
+    if now < state.next_due {
+        return None;
+    }
+    // periods starting at or after `end` are not paid
+    let last = match &state.schedule.end {
+        Some(end) => match position(&state.schedule.interval, end) {
+            Ok(end) if end <= state.next_due => return Some(0),
+            Ok(end) => now.min(end - 1),
+            Err(_) => now,
+        },
+        None => now,
+    };
+    Some((last - state.next_due) / interval_len(&state.schedule.interval) + 1)
+}
+
+fn interval_len(interval: &Duration) -> u64 {
+    match interval {
+        Duration::Blocks(n) | Duration::Seconds(n) => *n,
+    }
+}
+
+/// Height or time in seconds of `at`, which must use the unit of `interval`.
+fn position(interval: &Duration, at: &Scheduled) -> Result<u64, ContractError> {
+    match (interval, at) {
+        (Duration::Blocks(_), Scheduled::AtHeight(h)) => Ok(*h),
+        (Duration::Seconds(_), Scheduled::AtTime(t)) => Ok(t.seconds()),
+        _ => Err(ContractError::InvalidSchedule {}),
+    }
+}
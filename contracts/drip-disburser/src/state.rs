@@ -1,28 +1,276 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin, Uint128};
-use cw_storage_plus::Item;
-use cw_utils::Expiration;
-use drip_disburser_interface::msg::DurationBounds;
+use cosmwasm_std::{ensure, Addr, BlockInfo, Coin, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cw_utils::{Expiration, Scheduled};
+use drip_disburser_interface::msg::{
+    DurationBounds, KeeperBounty, LifecycleStatus, PayoutRecord, PayoutStatus, PriceBand, RetryPolicy, Schedule,
+};
+
+use crate::asset::Asset;
+use crate::ContractError;
 
 #[cw_serde]
 pub struct Config {
-    pub settlement_asset_limit: Coin,
+    pub settlement_asset: Asset,
+    pub settlement_asset_limit: Uint128,
     pub quote_asset_limit: Coin,
     pub admin: Addr,
+    pub refund_addr: Addr,
     pub recipient_addr: Addr,
+    pub recipients: Vec<Recipient>,
     pub price_feeder_addr: Addr,
-    pub payment_initiator_addrs: Vec<Addr>,
     pub funding_expiration: Expiration,
     pub payout_duration_bounds: DurationBounds,
+    pub price_band: Option<PriceBand>,
+    /// Prices older than this many seconds are refused, `None` disables the check
+    pub max_price_age: Option<u64>,
+    /// Prices observed up to this many seconds before a request are accepted for it;
+    /// with `None` only prices observed after the request are
+    pub cached_price_age: Option<u64>,
+    /// Decimal exponents of the assets, scaling the whole-token oracle price to base units
+    pub quote_decimals: u32,
+    pub settlement_decimals: u32,
+    /// Timeout of ICS-20 payouts, in seconds
+    pub ibc_transfer_timeout: u64,
+    pub keeper_bounty: Option<KeeperBounty>,
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+pub const DEFAULT_IBC_TRANSFER_TIMEOUT: u64 = 600;
+
+/// Largest accepted decimal exponent, keeping the price scaling within `Uint256`
+pub const MAX_DECIMALS: u32 = 18;
+
+pub const TOTAL_WEIGHT_BPS: u16 = 10_000;
+
+#[cw_serde]
+pub struct Recipient {
+    /// Local address, or a bech32 address on the chain behind `channel_id`
+    pub addr: String,
+    pub weight_bps: u16,
+    pub channel_id: Option<String>,
+}
+
+#[cw_serde]
+pub struct Initiator {
+    /// Lifetime quote allowance, unlimited when `None`
+    pub allowance: Option<Uint128>,
+    /// Quote amount consumed so far, including pending payouts
+    pub used: Uint128,
+    pub expires_at: Option<Expiration>,
+    pub max_per_request: Option<Uint128>,
+}
+
+impl Initiator {
+    pub fn unlimited() -> Self {
+        Initiator {
+            allowance: None,
+            used: Uint128::zero(),
+            expires_at: None,
+            max_per_request: None,
+        }
+    }
+
+    /// Largest quote amount a single request may ask for, `None` when unlimited
+    pub fn request_limit(&self) -> Option<Uint128> {
+        let left = self.allowance.map(|a| a.saturating_sub(self.used));
+        match (left, self.max_per_request) {
+            (Some(left), Some(max)) => Some(left.min(max)),
+            (left, max) => left.or(max),
+        }
+    }
 }
 
 #[cw_serde]
 pub struct PendingPayout {
-    // pub requester: Addr,
+    pub seq: u64,
+    pub requester: Addr,
     // pub amount_in_quote: Option<Coin>,
     pub amount_in_quote: Uint128,
     pub expires_at: Expiration,
+    /// Price limits given with the request
+    pub price_band: PriceBand,
+    /// Block time of the request; prices observed before it are refused
+    pub created_at: Timestamp,
+    /// Price requests sent so far
+    pub attempts: u32,
+    /// Set after a timeout while the retry waits for its backoff
+    pub retry_at: Option<Scheduled>,
+}
+
+impl PendingPayout {
+    /// Ledger record for this payout closed at `block` with nothing paid out.
+    pub fn record(&self, block: &BlockInfo, status: PayoutStatus) -> PayoutRecord {
+        PayoutRecord {
+            id: self.seq,
+            requester: self.requester.clone(),
+            amount_in_quote: self.amount_in_quote,
+            price: None,
+            settlement_amount: Uint128::zero(),
+            recipients: vec![],
+            height: block.height,
+            time: block.time,
+            status,
+            reason: None,
+            returned: Uint128::zero(),
+        }
+    }
+}
+
+/// Lifetime totals paid out, counted against `quote_asset_limit` and `settlement_asset_limit`
+#[cw_serde]
+#[derive(Default)]
+pub struct BudgetUsage {
+    pub quote: Uint128,
+    pub settlement: Uint128,
+    /// Quote amount held by pending payouts
+    pub quote_reserved: Uint128,
+}
+
+impl BudgetUsage {
+    pub fn quote_remaining(&self, cfg: &Config) -> Uint128 {
+        cfg.quote_asset_limit.amount.saturating_sub(self.quote)
+    }
+
+    /// Quote budget left for new requests once pending reservations are taken into account
+    pub fn quote_available(&self, cfg: &Config) -> Uint128 {
+        self.quote_remaining(cfg).saturating_sub(self.quote_reserved)
+    }
+
+    pub fn settlement_remaining(&self, cfg: &Config) -> Uint128 {
+        cfg.settlement_asset_limit.saturating_sub(self.settlement)
+    }
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
-pub const PENDING_PAYOUT: Item<PendingPayout> = Item::new("pending_payout");
+pub const STATUS: Item<LifecycleStatus> = Item::new("status");
+/// Upper bound on concurrently pending payouts
+pub const MAX_PENDING_PAYOUTS: usize = 32;
+/// Payouts waiting for an oracle price, keyed by request sequence
+pub const PENDING_PAYOUTS: Map<u64, PendingPayout> = Map::new("pending_payouts");
+/// Last payout request sequence, passed to the oracle and echoed back as `request_id`
+pub const PAYOUT_SEQ: Item<u64> = Item::new("payout_seq");
+pub const BUDGET_USAGE: Item<BudgetUsage> = Item::new("budget_usage");
+pub const INITIATORS: Map<&Addr, Initiator> = Map::new("initiators");
+pub struct PayoutIndexes<'a> {
+    /// Status code and block time in nanoseconds
+    pub status: MultiIndex<'a, (u8, u64), PayoutRecord, u64>,
+    /// Block time in nanoseconds
+    pub time: MultiIndex<'a, u64, PayoutRecord, u64>,
+}
+
+impl IndexList<PayoutRecord> for PayoutIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PayoutRecord>> + '_> {
+        let v: Vec<&dyn Index<PayoutRecord>> = vec![&self.status, &self.time];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Payout history keyed by the payout request sequence
+pub fn payouts<'a>() -> IndexedMap<u64, PayoutRecord, PayoutIndexes<'a>> {
+    let indexes = PayoutIndexes {
+        status: MultiIndex::new(
+            |_, r| (status_code(&r.status), r.time.nanos()),
+            "payouts",
+            "payouts__status",
+        ),
+        time: MultiIndex::new(|_, r| r.time.nanos(), "payouts", "payouts__time"),
+    };
+    IndexedMap::new("payouts", indexes)
+}
+
+pub fn status_code(status: &PayoutStatus) -> u8 {
+    match status {
+        PayoutStatus::Settled => 0,
+        PayoutStatus::TimedOut => 1,
+        PayoutStatus::Cancelled => 2,
+        PayoutStatus::Failed => 3,
+    }
+}
+
+pub fn ensure_active(storage: &dyn Storage) -> Result<(), ContractError> {
+    let status = STATUS.load(storage)?;
+    ensure!(
+        status == LifecycleStatus::Active,
+        ContractError::InvalidStatus { status }
+    );
+    Ok(())
+}
+
+/// Refuses changes once the disburser is terminated or expired
+pub fn ensure_open(storage: &dyn Storage) -> Result<(), ContractError> {
+    let status = STATUS.load(storage)?;
+    ensure!(
+        matches!(status, LifecycleStatus::Active | LifecycleStatus::Paused),
+        ContractError::InvalidStatus { status }
+    );
+    Ok(())
+}
+#[cw_serde]
+pub struct ScheduleState {
+    pub schedule: Schedule,
+    /// Height or time, in seconds, at which the next period is due
+    pub next_due: u64,
+}
+
+/// Upper bound on schedules, all of which are walked by every crank
+pub const MAX_SCHEDULES: usize = 16;
+pub const SCHEDULES: Map<u64, ScheduleState> = Map::new("schedules");
+pub const SCHEDULE_SEQ: Item<u64> = Item::new("schedule_seq");
+
+/// ICS-20 payout share waiting for its acknowledgement
+#[cw_serde]
+pub struct IbcTransfer {
+    pub payout_id: u64,
+    pub requester: Addr,
+    pub channel_id: String,
+    pub amount: Uint128,
+    /// Part of the quote paid out that this share accounts for
+    pub quote_amount: Uint128,
+}
+
+/// In-flight ICS-20 payouts keyed by source channel and packet sequence
+pub const IBC_TRANSFERS: Map<(&str, u64), IbcTransfer> = Map::new("ibc_transfers");
+
+/// Upper bound on distinct funders, all of which are refunded by the close-out
+pub const MAX_FUNDERS: usize = 64;
+/// Settlement tokens deposited through `Fund`, used to split refunds pro rata
+pub const FUNDERS: Map<&Addr, Uint128> = Map::new("funders");
+pub const TOTAL_FUNDED: Item<Uint128> = Item::new("total_funded");
+
+/// Removes a pending payout and releases its quote reservation.
+pub fn take_pending(storage: &mut dyn Storage, seq: u64) -> Result<PendingPayout, ContractError> {
+    let pending = PENDING_PAYOUTS
+        .may_load(storage, seq)?
+        .ok_or(ContractError::UnknownPayoutRequest { request_id: seq })?;
+    PENDING_PAYOUTS.remove(storage, seq);
+
+    let mut usage = BUDGET_USAGE.load(storage)?;
+    usage.quote_reserved = usage.quote_reserved.saturating_sub(pending.amount_in_quote);
+    BUDGET_USAGE.save(storage, &usage)?;
+
+    Ok(pending)
+}
+
+/// Closes a pending payout that did not settle and records it in the ledger.
+pub fn close_pending(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    seq: u64,
+    status: PayoutStatus,
+) -> Result<PendingPayout, ContractError> {
+    let pending = take_pending(storage, seq)?;
+    release_allowance(storage, &pending.requester, pending.amount_in_quote)?;
+    let record = pending.record(block, status);
+    payouts().save(storage, record.id, &record)?;
+    Ok(pending)
+}
+
+/// Gives unspent quote back to the initiator's allowance, if the initiator still exists.
+pub fn release_allowance(storage: &mut dyn Storage, initiator: &Addr, amount: Uint128) -> StdResult<()> {
+    if let Some(mut i) = INITIATORS.may_load(storage, initiator)? {
+        i.used = i.used.saturating_sub(amount);
+        INITIATORS.save(storage, initiator, &i)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use cosmwasm_std::testing::MockApi;
+use cosmwasm_std::{
+    coin, coins, to_json_binary, Binary, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Response,
+    StdResult, Uint128,
+};
+use cw_multi_test::{App, ContractWrapper, Executor};
+use cw_utils::Expiration;
+use drip_disburser::contract::{execute, instantiate, query, reply};
+use drip_disburser_interface::msg::{
+    DurationBounds, DurationLimit, ExecuteMsg, FunderInfo, FundersResponse, InstantiateMsg, PayoutRecord,
+    PayoutStatus, QueryMsg, SettlementAsset,
+};
+use drip_price_oracle::contract as oracle;
+use drip_price_oracle_interface::msg::{
+    AdapterConfig, AdapterExecuteMsg, ExecuteMsg as OracleMsg, InstantiateMsg as OracleInstantiateMsg,
+    PriceKey,
+};
+
+// answers are fed to the oracle by hand
+fn adapter_instantiate(_: DepsMut, _: Env, _: MessageInfo, _: Empty) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn adapter_execute(_: DepsMut, _: Env, _: MessageInfo, _: AdapterExecuteMsg) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn adapter_query(_: Deps, _: Env, _: Empty) -> StdResult<Binary> {
+    to_json_binary(&Empty {})
+}
+
+#[test]
+fn cached_oracle_price_settles_the_payout_in_the_same_transaction() {
+    let api = MockApi::default();
+    let owner = api.addr_make("owner");
+    let initiator = api.addr_make("initiator");
+    let recipient = api.addr_make("recipient");
+    let other_consumer = api.addr_make("consumer");
+    let mut app = App::new(|router, _, storage| {
+        router.bank.init_balance(storage, &owner, coins(1_000, "SETTLE")).unwrap();
+    });
+
+    let adapter_code = app.store_code(Box::new(ContractWrapper::new(
+        adapter_execute,
+        adapter_instantiate,
+        adapter_query,
+    )));
+    let oracle_code = app.store_code(Box::new(
+        ContractWrapper::new(oracle::execute, oracle::instantiate, oracle::query).with_reply(oracle::reply),
+    ));
+    let disburser_code = app.store_code(Box::new(ContractWrapper::new(execute, instantiate, query).with_reply(reply)));
+
+    let adapter = app
+        .instantiate_contract(adapter_code, owner.clone(), &Empty {}, &[], "adapter", None)
+        .unwrap();
+    let oracle_msg = OracleInstantiateMsg {
+        adapters: Some(vec![AdapterConfig {
+            adapter: adapter.clone(),
+            pairs: vec![PriceKey { base: "SETTLE".to_string(), quote: "QUOTE".to_string() }],
+        }]),
+        admin: owner.clone(),
+        keeper_bounty: None,
+    };
+    let oracle_addr = app
+        .instantiate_contract(oracle_code, owner.clone(), &oracle_msg, &[], "oracle", None)
+        .unwrap();
+    let msg = InstantiateMsg {
+        settlement_asset: SettlementAsset::Native { denom: "SETTLE".to_string() },
+        settlement_asset_limit: Uint128::new(1_000),
+        quote_asset_limit: coin(1_000, "QUOTE"),
+        admin: None,
+        refund_addr: None,
+        recipient_addr: recipient.to_string(),
+        recipients: None,
+        oracle_addr: oracle_addr.to_string(),
+        payment_initiator_addrs: vec![initiator.to_string()],
+        funding_expiration: Expiration::Never {},
+        payout_duration_bounds: DurationBounds {
+            default: DurationLimit { blocks: 10, seconds: 0 },
+            max: None,
+            min: None,
+        },
+        price_band: None,
+        max_price_age: Some(600),
+        cached_price_age: Some(60),
+        quote_decimals: Some(0),
+        settlement_decimals: Some(0),
+        ibc_transfer_timeout: None,
+        keeper_bounty: None,
+        retry_policy: None,
+        funder: None,
+    };
+    // funded on instantiation, credited to the sender
+    let disburser = app
+        .instantiate_contract(disburser_code, owner.clone(), &msg, &coins(1_000, "SETTLE"), "disburser", None)
+        .unwrap();
+    let funders: FundersResponse = app
+        .wrap()
+        .query_wasm_smart(&disburser, &QueryMsg::Funders { start_after: None, limit: None })
+        .unwrap();
+    assert_eq!(funders.funders, vec![FunderInfo { addr: owner, deposited: Uint128::new(1_000) }]);
+
+    // another consumer's request leaves a price of 2 QUOTE per SETTLE in the oracle cache
+    let height = app.block_info().height;
+    let request = OracleMsg::RequestPrice {
+        base: "SETTLE".to_string(),
+        quote: "QUOTE".to_string(),
+        expiration: Expiration::AtHeight(height + 10),
+        valid_from: None,
+        sequence: None,
+    };
+    app.execute_contract(other_consumer, oracle_addr.clone(), &request, &[]).unwrap();
+    let answer = OracleMsg::OnPriceResponse {
+        request_id: 1,
+        price: Decimal::percent(200),
+        price_timestamp: app.block_info().time.seconds(),
+    };
+    app.execute_contract(adapter, oracle_addr, &answer, &[]).unwrap();
+    app.update_block(|block| {
+        block.height += 1;
+        block.time = block.time.plus_seconds(5);
+    });
+
+    // the price predates the request but is recent enough, so the payout settles at once
+    let request_payout = ExecuteMsg::RequestPayout {
+        amount_in_quote: Some(Uint128::new(100)),
+        duration_limit: None,
+        replace_pending: None,
+        min_price: None,
+        max_price: None,
+    };
+    app.execute_contract(initiator, disburser.clone(), &request_payout, &[]).unwrap();
+
+    assert_eq!(app.wrap().query_balance(&recipient, "SETTLE").unwrap().amount, Uint128::new(50));
+    let record: PayoutRecord = app.wrap().query_wasm_smart(&disburser, &QueryMsg::Payout { id: 1 }).unwrap();
+    assert_eq!(record.status, PayoutStatus::Settled);
+    assert_eq!(record.price, Some(Decimal::percent(200)));
+}
@@ -60,18 +60,10 @@ pub fn execute(
             valid_from,
             expiration,
         } => execute::request_price(deps, info.sender, base, quote, sequence, valid_from, expiration),
-        ExecuteMsg::AddWhiteListedContract {
-            contract_address,
-        } => unimplemented!(),
-        ExecuteMsg::RemoveWhitelistedContract {
-            contract_address,
-        } => unimplemented!(),
-        ExecuteMsg::ModifyTwapSetting {
-            twap_setting,
-        } => unimplemented!(),
-        ExecuteMsg::ModifyAdmin {
-            new_admin,
-        } => unimplemented!(),
+        ExecuteMsg::AddWhiteListedContract { .. } => unimplemented!(),
+        ExecuteMsg::RemoveWhitelistedContract { .. } => unimplemented!(),
+        ExecuteMsg::ModifyTwapSetting { .. } => unimplemented!(),
+        ExecuteMsg::ModifyAdmin { .. } => unimplemented!(),
     }
 }
 
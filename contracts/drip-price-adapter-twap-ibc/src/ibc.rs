@@ -1,4 +1,5 @@
 use crate::ack::AckWrapper;
+use crate::msg::CallbackMsg;
 use crate::state::{data_hash, ChannelInfo, RequestInfo, CHANNEL_INFO, REQ_BY_DATA};
 use crate::ContractError;
 #[cfg(not(feature = "library"))]
@@ -9,7 +10,6 @@ use cosmwasm_std::{
     IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, StdError,
     StdResult, WasmMsg,
 };
-use drip_disburser_interface::msg::ExecuteMsg::OnPayoutResponse;
 use prost::Message;
 use std::str::FromStr;
 use crate::icq::{AcknowledgementResult, CosmosResponse, InterchainQueryPacketAck};
@@ -100,7 +100,7 @@ pub fn ibc_packet_receive(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_packet_ack(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcPacketAckMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     let key = data_hash(&msg.original_packet.data);
@@ -117,7 +117,7 @@ pub fn ibc_packet_ack(
                 let packet_ack: InterchainQueryPacketAck = from_json(ack_result.result)?;
 
                 // Process TWAP response and create callbacks
-                let callbacks = process_twap_response(deps, packet_ack, request_list)?;
+                let callbacks = process_twap_response(deps, &env, packet_ack, request_list)?;
 
                 Ok(IbcBasicResponse::new()
                     .add_messages(callbacks)
@@ -192,14 +192,16 @@ pub fn validate_order_and_version(
 /// and creation of callback messages.
 fn process_twap_response(
     _deps: DepsMut,
+    env: &Env,
     packet_ack: InterchainQueryPacketAck,
     request_info: Vec<RequestInfo>
 ) -> Result<Vec<CosmosMsg>, ContractError> {
     // Extract TWAP price from the packet acknowledgment
     let twap_price = extract_twap_price_from_ack(&packet_ack)?;
 
-    // Create callback messages for each request
-    create_callback_messages(request_info, twap_price)
+    // The TWAP runs up to the moment the query was answered, so the ack block time is
+    // the closest observation time available
+    create_callback_messages(request_info, twap_price, env.block.time.seconds())
 }
 
 /// Extracts the TWAP price from an interchain query packet acknowledgment.
@@ -237,16 +239,18 @@ fn extract_twap_price_from_ack(packet_ack: &InterchainQueryPacketAck) -> Result<
 
 /// Creates callback messages for each request with the given TWAP price.
 ///
-/// Each message invokes the OnPayoutResponse handler on the requester contract.
+/// Each message answers the oracle that forwarded the request, which calls back its own requesters.
 fn create_callback_messages(
     request_info: Vec<RequestInfo>,
-    twap_price: Decimal
+    twap_price: Decimal,
+    price_timestamp: u64,
 ) -> Result<Vec<CosmosMsg>, ContractError> {
     request_info
         .into_iter()
         .map(|info| -> StdResult<_> {
-            let exec_msg = OnPayoutResponse {
+            let exec_msg = CallbackMsg::OnPriceResponse {
                 price: twap_price,
+                price_timestamp,
                 request_id: info.sequence,
             };
 
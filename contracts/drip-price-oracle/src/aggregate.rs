@@ -0,0 +1,83 @@
+use cosmwasm_std::{ensure, Decimal, StdError, StdResult, Uint256};
+use drip_price_oracle_interface::msg::{
+    AdapterAnswer, AggregationConfig, AggregationMode, AggregationOutcome,
+};
+
+use crate::ContractError;
+
+pub fn validate_aggregation(aggregation: &AggregationConfig) -> Result<(), ContractError> {
+    let adapters = &aggregation.adapters;
+    let unique = adapters
+        .iter()
+        .enumerate()
+        .all(|(i, adapter)| !adapters[..i].contains(adapter));
+    let trim_ok = match aggregation.mode {
+        AggregationMode::Median => true,
+        AggregationMode::TrimmedMean { trim } => trim < Decimal::percent(50),
+    };
+    ensure!(
+        unique
+            && aggregation.quorum > 0
+            && aggregation.quorum as usize <= adapters.len()
+            && trim_ok,
+        ContractError::InvalidAggregation {}
+    );
+    Ok(())
+}
+
+/// Settles `answers` once a quorum of them lies within the deviation of their median,
+/// `None` while it does not.
+pub fn aggregate(
+    aggregation: &AggregationConfig,
+    answers: &[AdapterAnswer],
+) -> StdResult<Option<AggregationOutcome>> {
+    let mut prices = answers.iter().map(|a| a.price).collect::<Vec<_>>();
+    let Some(center) = median(&mut prices) else {
+        return Ok(None);
+    };
+    let tolerance = center.saturating_mul(aggregation.max_deviation);
+    let accepted = answers
+        .iter()
+        .filter(|a| a.price.abs_diff(center) <= tolerance)
+        .collect::<Vec<_>>();
+    if accepted.len() < aggregation.quorum as usize {
+        return Ok(None);
+    }
+
+    let mut prices = accepted.iter().map(|a| a.price).collect::<Vec<_>>();
+    let price = match aggregation.mode {
+        AggregationMode::Median => median(&mut prices).unwrap_or(center),
+        AggregationMode::TrimmedMean { trim } => trimmed_mean(&mut prices, trim)?,
+    };
+    Ok(Some(AggregationOutcome::Settled {
+        price,
+        price_timestamp: accepted.iter().map(|a| a.price_timestamp).min().unwrap_or_default(),
+        accepted: accepted.into_iter().map(|a| a.adapter.clone()).collect(),
+    }))
+}
+
+/// Middle price, the mean of the two middle ones for an even count
+fn median(prices: &mut [Decimal]) -> Option<Decimal> {
+    prices.sort();
+    let mid = prices.len() / 2;
+    match prices.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(prices[mid]),
+        _ => Some(prices[mid - 1] + (prices[mid] - prices[mid - 1]) / Decimal::percent(200)),
+    }
+}
+
+fn trimmed_mean(prices: &mut [Decimal], trim: Decimal) -> StdResult<Decimal> {
+    prices.sort();
+    let cut = Decimal::from_ratio(prices.len() as u128, 1u128)
+        .saturating_mul(trim)
+        .to_uint_floor()
+        .u128() as usize;
+    let kept = &prices[cut..prices.len() - cut];
+    // in atomics, so that the sum cannot overflow
+    let sum = kept
+        .iter()
+        .fold(Uint256::zero(), |sum, price| sum + Uint256::from(price.atomics()));
+    let mean = sum / Uint256::from(kept.len() as u128);
+    Ok(Decimal::new(mean.try_into().map_err(StdError::from)?))
+}
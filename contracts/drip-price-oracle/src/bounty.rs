@@ -0,0 +1,10 @@
+use cosmwasm_std::ensure;
+pub use drip_keeper_bounty::keeper_reward;
+use drip_price_oracle_interface::msg::KeeperBounty;
+
+use crate::ContractError;
+
+pub fn validate_keeper_bounty(bounty: &KeeperBounty) -> Result<(), ContractError> {
+    ensure!(bounty.is_valid(), ContractError::InvalidKeeperBounty {});
+    Ok(())
+}
@@ -1,7 +1,11 @@
 #[cfg(not(feature = "library"))]
-use cosmwasm_std::{entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
-use cosmwasm_std::{Reply, SubMsgResponse, SubMsgResult};
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult};
 use cw2::set_contract_version;
+use crate::bounty::validate_keeper_bounty;
+use crate::execute::{self, CALLBACK_REPLY_ID};
+use crate::query;
+use crate::state::{Config, ADAPTERS, CONFIG};
 use crate::ContractError;
 use drip_price_oracle_interface::msg::{
     ExecuteMsg,
@@ -18,9 +22,24 @@ pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
-    _msg: InstantiateMsg,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    // todo:
+    if let Some(bounty) = &msg.keeper_bounty {
+        validate_keeper_bounty(bounty)?;
+    }
+    let cfg = Config {
+        admin: Some(deps.api.addr_validate(msg.admin.as_str())?),
+        keeper_bounty: msg.keeper_bounty,
+    };
+    CONFIG.save(deps.storage, &cfg)?;
+
+    for config in msg.adapters.unwrap_or_default() {
+        let adapter = deps.api.addr_validate(config.adapter.as_str())?;
+        for pair in config.pairs {
+            ADAPTERS.save(deps.storage, (&pair.base, &pair.quote), &adapter)?;
+        }
+    }
+
     // version for migrations
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     Ok(Response::new().add_attribute("action", "instantiate"))
@@ -28,45 +47,593 @@ pub fn instantiate(
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    _deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::UpdateAdmin { .. } => unimplemented!(),
-        ExecuteMsg::RequestPrice { .. } => unimplemented!(),
-        ExecuteMsg::OnPriceResponse { .. } => unimplemented!(),
-        ExecuteMsg::TimeoutExpiredRequests { .. } => unimplemented!(),
-        ExecuteMsg::AddAdapter { .. } => unimplemented!(),
-        ExecuteMsg::RemoveAdapter { .. } => unimplemented!(),
+        ExecuteMsg::UpdateAdmin { admin } => execute::update_admin(deps, info, admin),
+        ExecuteMsg::RequestPrice {
+            base,
+            quote,
+            expiration,
+            valid_from,
+            sequence,
+        } => execute::request_price(deps, env, info, base, quote, expiration, valid_from, sequence),
+        ExecuteMsg::OnPriceResponse {
+            request_id,
+            price,
+            price_timestamp,
+        } => execute::on_price_response(deps, info, request_id, price, price_timestamp),
+        ExecuteMsg::TimeoutExpiredRequests { batch_limit } => {
+            execute::timeout_expired_requests(deps, env, info, batch_limit)
+        }
+        ExecuteMsg::AddAdapter { adapter, pairs } => execute::add_adapter(deps, info, adapter, pairs),
+        ExecuteMsg::RemoveAdapter { adapter } => execute::remove_adapter(deps, info, adapter),
+        ExecuteMsg::SetAggregation { pair, aggregation } => {
+            execute::set_aggregation(deps, info, pair, aggregation)
+        }
+        ExecuteMsg::UpdateKeeperBounty { keeper_bounty } => {
+            execute::update_keeper_bounty(deps, info, keeper_bounty)
+        }
+        ExecuteMsg::UpdateRewardedRequesters { add, remove } => {
+            execute::update_rewarded_requesters(deps, info, add, remove)
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<Binary> {
-    unimplemented!()
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_json_binary(&query::config(deps)?),
+        QueryMsg::Adapter { base, quote } => to_json_binary(&query::adapter(deps, base, quote)?),
+        QueryMsg::Adapters { start_after, limit } => {
+            to_json_binary(&query::adapters(deps, start_after, limit)?)
+        }
+        QueryMsg::Aggregation { base, quote } => {
+            to_json_binary(&query::aggregation(deps, base, quote)?)
+        }
+        QueryMsg::AggregationRecord { request_id } => {
+            to_json_binary(&query::aggregation_record(deps, request_id)?)
+        }
+        QueryMsg::CachedPrice { base, quote } => {
+            to_json_binary(&query::cached_price(deps, base, quote)?)
+        }
+        QueryMsg::CachedPrices { start_after, limit } => {
+            to_json_binary(&query::cached_prices(deps, start_after, limit)?)
+        }
+        QueryMsg::RewardedRequesters { start_after, limit } => {
+            to_json_binary(&query::rewarded_requesters(deps, start_after, limit)?)
+        }
+    }
 }
 
+/// Only failed requester callbacks reply: the failure is recorded and the rest of the
+/// response goes through.
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
-    // match msg.id {
-    //     REPLY_ID_PRICE_RESPONSE => handle_price_response(deps, env, msg),
-    //     REPLY_ID_TIMEOUT => handle_timeout_response(deps, env, msg),
-    //     _ => Err(ContractError::UnknownReplyId {}),
-    // }
-
-    match msg {
-        Reply {
-            id: MY_REPLY_ID,
-            result: SubMsgResult::Ok(SubMsgResponse { msg_responses, .. }),
-            ..
-        } if !msg_responses.is_empty() => {
-            unimplemented!()
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        CALLBACK_REPLY_ID => {
+            let error = msg.result.into_result().err().unwrap_or_default();
+            Ok(Response::new()
+                .add_attribute("action", "callback_failed")
+                .add_attribute("error", error))
         }
-        _ => unimplemented!(),
+        id => Err(ContractError::UnknownReplyId { id }),
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::bounty::keeper_reward;
+    use drip_price_oracle_interface::msg::KeeperBounty;
+    use cosmwasm_std::coin;
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+
+    #[test]
+    fn keeper_reward_is_capped_per_block_and_by_the_pool() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let admin = deps.api.addr_make("admin");
+        deps.querier.bank.update_balance(&env.contract.address, vec![coin(75, "BOUNTY")]);
+
+        let bounty = KeeperBounty {
+            reward: coin(10, "BOUNTY"),
+            max_units_per_block: 5,
+        };
+        let msg = InstantiateMsg {
+            adapters: None,
+            admin: admin.clone(),
+            keeper_bounty: Some(bounty.clone()),
+        };
+        instantiate(deps.as_mut(), env.clone(), message_info(&admin, &[]), msg).unwrap();
+        let anyone = deps.api.addr_make("anyone");
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&anyone, &[]),
+            ExecuteMsg::UpdateKeeperBounty { keeper_bounty: None },
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let cfg = CONFIG.load(&deps.storage).unwrap();
+        let mut reward = |env: &Env, units| {
+            let deps = deps.as_mut();
+            keeper_reward(deps.storage, &deps.querier, env, cfg.keeper_bounty.as_ref(), units).unwrap()
+        };
+        assert_eq!(reward(&env, 3), Some(coin(30, "BOUNTY")));
+        assert_eq!(reward(&env, 3), Some(coin(20, "BOUNTY")));
+        assert_eq!(reward(&env, 1), None);
+
+        env.block.height += 1;
+        assert_eq!(reward(&env, 10), Some(coin(50, "BOUNTY")));
+    }
+
+    #[test]
+    fn requests_are_routed_to_the_pair_adapter_and_answered_back() {
+        use cosmwasm_std::{from_json, CosmosMsg, Decimal, SubMsg, WasmMsg};
+        use cw_utils::Expiration;
+        use drip_price_oracle_interface::msg::{AdapterConfig, AdapterExecuteMsg, AdaptersResponse, CallbackMsg, PriceKey};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = deps.api.addr_make("admin");
+        let adapter = deps.api.addr_make("adapter");
+        let disburser = deps.api.addr_make("disburser");
+        let pair = |base: &str| PriceKey { base: base.to_string(), quote: "uusdc".to_string() };
+        let msg = InstantiateMsg {
+            adapters: Some(vec![AdapterConfig { adapter: adapter.clone(), pairs: vec![pair("uatom")] }]),
+            admin: admin.clone(),
+            keeper_bounty: None,
+        };
+        instantiate(deps.as_mut(), env.clone(), message_info(&admin, &[]), msg).unwrap();
+
+        let request = |base: &str, sequence| ExecuteMsg::RequestPrice {
+            base: base.to_string(),
+            quote: "uusdc".to_string(),
+            expiration: Expiration::AtTime(env.block.time.plus_seconds(60)),
+            valid_from: None,
+            sequence,
+        };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&disburser, &[]), request("uosmo", Some(7))).unwrap_err();
+        assert!(matches!(err, ContractError::UnsupportedPair { .. }));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&admin, &[]),
+            ExecuteMsg::AddAdapter { adapter: adapter.clone(), pairs: vec![pair("uosmo")] },
+        ).unwrap();
+        let routes: AdaptersResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::Adapters { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert_eq!(routes.routes.len(), 2);
+
+        // forwarded under the oracle request id
+        let resp = execute(deps.as_mut(), env.clone(), message_info(&disburser, &[]), request("uatom", Some(7))).unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: adapter.to_string(),
+                msg: to_json_binary(&AdapterExecuteMsg::RequestPrice {
+                    base: "uatom".to_string(),
+                    quote: "uusdc".to_string(),
+                    sequence: 1,
+                    valid_from: None,
+                    expiration: Expiration::AtTime(env.block.time.plus_seconds(60)),
+                }).unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // only the adapter answers, and the requester gets its own sequence back
+        let answer = ExecuteMsg::OnPriceResponse {
+            request_id: 1,
+            price: Decimal::percent(250),
+            price_timestamp: env.block.time.seconds(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&disburser, &[]), answer.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let resp = execute(deps.as_mut(), env.clone(), message_info(&adapter, &[]), answer.clone()).unwrap();
+        let callback = WasmMsg::Execute {
+            contract_addr: disburser.to_string(),
+            msg: to_json_binary(&CallbackMsg::OnPayoutResponse {
+                price: Decimal::percent(250),
+                price_timestamp: env.block.time.seconds(),
+                request_id: 7,
+            }).unwrap(),
+            funds: vec![],
+        };
+        assert_eq!(resp.messages, vec![SubMsg::reply_on_error(callback, CALLBACK_REPLY_ID)]);
+        let err = execute(deps.as_mut(), env.clone(), message_info(&adapter, &[]), answer).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownRequest { request_id: 1 }));
+
+        execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::RemoveAdapter { adapter }).unwrap();
+        let routes: AdaptersResponse = from_json(
+            query(deps.as_ref(), env, QueryMsg::Adapters { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert!(routes.routes.is_empty());
+    }
+
+    #[test]
+    fn compatible_requests_share_one_adapter_request() {
+        use cosmwasm_std::{Decimal, Timestamp};
+        use cw_utils::Expiration;
+        use drip_price_oracle_interface::msg::{AdapterConfig, PriceKey};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let admin = deps.api.addr_make("admin");
+        let adapter = deps.api.addr_make("adapter");
+        let alice = message_info(&deps.api.addr_make("alice"), &[]);
+        let bob = message_info(&deps.api.addr_make("bob"), &[]);
+        let msg = InstantiateMsg {
+            adapters: Some(vec![AdapterConfig {
+                adapter: adapter.clone(),
+                pairs: vec![PriceKey { base: "uatom".to_string(), quote: "uusdc".to_string() }],
+            }]),
+            admin: admin.clone(),
+            keeper_bounty: None,
+        };
+        instantiate(deps.as_mut(), env.clone(), message_info(&admin, &[]), msg).unwrap();
+
+        let start = env.block.time;
+        let request = |expires_in, valid_from: Option<Timestamp>, sequence| ExecuteMsg::RequestPrice {
+            base: "uatom".to_string(),
+            quote: "uusdc".to_string(),
+            expiration: Expiration::AtTime(start.plus_seconds(expires_in)),
+            valid_from: valid_from.map(Expiration::AtTime),
+            sequence: Some(sequence),
+        };
+        let sent = |resp: &Response| resp.messages.len();
+
+        // same pair in the same block: one adapter request
+        let resp = execute(deps.as_mut(), env.clone(), alice.clone(), request(60, None, 1)).unwrap();
+        assert_eq!(sent(&resp), 1);
+        let resp = execute(deps.as_mut(), env.clone(), bob.clone(), request(30, None, 1)).unwrap();
+        assert_eq!(sent(&resp), 0);
+
+        // later on, a fresh price needs a new request unless an older one is acceptable
+        env.block.height += 1;
+        env.block.time = start.plus_seconds(5);
+        let resp = execute(deps.as_mut(), env.clone(), bob.clone(), request(90, None, 2)).unwrap();
+        assert_eq!(sent(&resp), 1);
+        let resp = execute(deps.as_mut(), env.clone(), alice.clone(), request(120, Some(start), 2)).unwrap();
+        assert_eq!(sent(&resp), 0);
+        // requests 1 and 2 both expire too late for a 50 second deadline
+        env.block.height += 1;
+        env.block.time = start.plus_seconds(10);
+        let resp = execute(deps.as_mut(), env.clone(), alice.clone(), request(50, Some(start), 3)).unwrap();
+        assert_eq!(sent(&resp), 1);
+
+        // one answer fans out to every requester waiting on it
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&adapter, &[]),
+            ExecuteMsg::OnPriceResponse {
+                request_id: 1,
+                price: Decimal::one(),
+                price_timestamp: env.block.time.seconds(),
+            },
+        ).unwrap();
+        assert_eq!(resp.messages.len(), 2);
+        let inflight = crate::state::INFLIGHT_REQUESTS
+            .range(&deps.storage, None, None, cosmwasm_std::Order::Ascending)
+            .map(|item| item.unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(inflight, vec![2, 3]);
+
+        // request 3 is past its deadline and no longer joined, request 2 still fits;
+        // valid from the height of request 2, which the cached price predates
+        env.block.height += 1;
+        env.block.time = start.plus_seconds(50);
+        let late = ExecuteMsg::RequestPrice {
+            base: "uatom".to_string(),
+            quote: "uusdc".to_string(),
+            expiration: Expiration::AtTime(start.plus_seconds(200)),
+            valid_from: Some(Expiration::AtHeight(env.block.height - 2)),
+            sequence: Some(3),
+        };
+        let resp = execute(deps.as_mut(), env.clone(), bob.clone(), late).unwrap();
+        assert_eq!(sent(&resp), 0);
+        assert!(resp.attributes.iter().any(|a| a.key == "request_id" && a.value == "2"));
+    }
+
+    #[test]
+    fn cached_prices_answer_requests_valid_from_before_them() {
+        use cosmwasm_std::{from_json, CosmosMsg, Decimal, WasmMsg};
+        use cw_utils::Expiration;
+        use drip_price_oracle_interface::msg::{
+            AdapterConfig, CachedPriceResponse, CachedPricesResponse, CallbackMsg, PriceKey,
+        };
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let admin = deps.api.addr_make("admin");
+        let adapter = deps.api.addr_make("adapter");
+        let disburser = message_info(&deps.api.addr_make("disburser"), &[]);
+        let pair = PriceKey { base: "uatom".to_string(), quote: "uusdc".to_string() };
+        let msg = InstantiateMsg {
+            adapters: Some(vec![AdapterConfig { adapter: adapter.clone(), pairs: vec![pair.clone()] }]),
+            admin: admin.clone(),
+            keeper_bounty: None,
+        };
+        instantiate(deps.as_mut(), env.clone(), message_info(&admin, &[]), msg).unwrap();
+
+        let start = env.block.time;
+        let request = |valid_from: Option<Expiration>, sequence| ExecuteMsg::RequestPrice {
+            base: "uatom".to_string(),
+            quote: "uusdc".to_string(),
+            expiration: Expiration::AtTime(start.plus_seconds(600)),
+            valid_from,
+            sequence: Some(sequence),
+        };
+        let answer = |request_id, percent, observed: u64| ExecuteMsg::OnPriceResponse {
+            request_id,
+            price: Decimal::percent(percent),
+            price_timestamp: start.plus_seconds(observed).seconds(),
+        };
+        let cached_price = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> Option<CachedPriceResponse> {
+            from_json(
+                query(deps.as_ref(), mock_env(), QueryMsg::CachedPrice { base: "uatom".to_string(), quote: "uusdc".to_string() })
+                    .unwrap(),
+            ).unwrap()
+        };
+        assert_eq!(cached_price(&deps), None);
+
+        // two requests in flight, answered out of order
+        let sent_at = env.block.height;
+        execute(deps.as_mut(), env.clone(), disburser.clone(), request(None, 1)).unwrap();
+        env.block.height += 1;
+        env.block.time = start.plus_seconds(5);
+        execute(deps.as_mut(), env.clone(), disburser.clone(), request(None, 2)).unwrap();
+        execute(deps.as_mut(), env.clone(), message_info(&adapter, &[]), answer(2, 300, 6)).unwrap();
+        execute(deps.as_mut(), env.clone(), message_info(&adapter, &[]), answer(1, 200, 1)).unwrap();
+        let cached = CachedPriceResponse {
+            pair: pair.clone(),
+            price: Decimal::percent(300),
+            price_timestamp: start.plus_seconds(6).seconds(),
+            block_height: sent_at + 1,
+        };
+        assert_eq!(cached_price(&deps), Some(cached.clone()));
+        let all: CachedPricesResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::CachedPrices { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert_eq!(all.prices, vec![cached]);
+
+        // observed after valid_from: answered at once without the adapter
+        env.block.height += 1;
+        env.block.time = start.plus_seconds(20);
+        for valid_from in [Expiration::AtTime(start.plus_seconds(6)), Expiration::AtHeight(sent_at + 1)] {
+            let resp = execute(deps.as_mut(), env.clone(), disburser.clone(), request(Some(valid_from), 3)).unwrap();
+            assert_eq!(resp.messages.len(), 1);
+            assert_eq!(
+                resp.messages[0].msg,
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: disburser.sender.to_string(),
+                    msg: to_json_binary(&CallbackMsg::OnPayoutResponse {
+                        price: Decimal::percent(300),
+                        price_timestamp: start.plus_seconds(6).seconds(),
+                        request_id: 3,
+                    }).unwrap(),
+                    funds: vec![],
+                })
+            );
+        }
+
+        // observed too early, or a fresh price wanted: left to the adapter
+        let has = |resp: &Response, key: &str| resp.attributes.iter().any(|a| a.key == key);
+        let too_early = Some(Expiration::AtTime(start.plus_seconds(7)));
+        let resp = execute(deps.as_mut(), env.clone(), disburser.clone(), request(too_early, 4)).unwrap();
+        assert!(has(&resp, "adapter") && !has(&resp, "cached"));
+        let resp = execute(deps.as_mut(), env.clone(), disburser.clone(), request(None, 5)).unwrap();
+        assert!(has(&resp, "coalesced") && !has(&resp, "cached"));
+    }
+
+    #[test]
+    fn expired_requests_are_timed_out_in_batches() {
+        use cosmwasm_std::{BankMsg, CosmosMsg, Decimal, SubMsg, WasmMsg};
+        use cw_utils::Expiration;
+        use drip_price_oracle_interface::msg::{AdapterConfig, CallbackMsg, PriceKey};
+        use crate::state::{ADAPTER_REQUESTS, INFLIGHT_REQUESTS, PENDING_EXPIRATIONS};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let admin = deps.api.addr_make("admin");
+        let adapter = deps.api.addr_make("adapter");
+        let alice = message_info(&deps.api.addr_make("alice"), &[]);
+        let bob = message_info(&deps.api.addr_make("bob"), &[]);
+        let keeper = deps.api.addr_make("keeper");
+        deps.querier.bank.update_balance(&env.contract.address, vec![coin(100, "BOUNTY")]);
+        let msg = InstantiateMsg {
+            adapters: Some(vec![AdapterConfig {
+                adapter: adapter.clone(),
+                pairs: vec![PriceKey { base: "uatom".to_string(), quote: "uusdc".to_string() }],
+            }]),
+            admin: admin.clone(),
+            keeper_bounty: Some(KeeperBounty { reward: coin(10, "BOUNTY"), max_units_per_block: 10 }),
+        };
+        instantiate(deps.as_mut(), env.clone(), message_info(&admin, &[]), msg).unwrap();
+        // only alice's requests are worth a bounty
+        let rewarded = ExecuteMsg::UpdateRewardedRequesters { add: vec![alice.sender.to_string()], remove: vec![] };
+        let err = execute(deps.as_mut(), env.clone(), alice.clone(), rewarded.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), rewarded).unwrap();
+
+        let start = env.block.clone();
+        let request = |expiration, sequence| ExecuteMsg::RequestPrice {
+            base: "uatom".to_string(),
+            quote: "uusdc".to_string(),
+            expiration,
+            valid_from: None,
+            sequence: Some(sequence),
+        };
+        // request 1 shared by alice and bob, request 2 by bob alone
+        execute(deps.as_mut(), env.clone(), alice.clone(), request(Expiration::AtHeight(start.height + 10), 1)).unwrap();
+        execute(deps.as_mut(), env.clone(), bob.clone(), request(Expiration::AtTime(start.time.plus_seconds(30)), 1)).unwrap();
+        env.block.height += 1;
+        env.block.time = start.time.plus_seconds(5);
+        execute(deps.as_mut(), env.clone(), bob.clone(), request(Expiration::AtTime(start.time.plus_seconds(20)), 2)).unwrap();
+
+        let timeout = |batch_limit| ExecuteMsg::TimeoutExpiredRequests { batch_limit };
+        let err = execute(deps.as_mut(), env.clone(), message_info(&keeper, &[]), timeout(10)).unwrap_err();
+        assert!(matches!(err, ContractError::NothingExpired {}));
+
+        // all three expired: the earliest height, then the earliest time, within the limit
+        env.block.height = start.height + 10;
+        env.block.time = start.time.plus_seconds(30);
+        let timed_out = |requester: &MessageInfo, request_id| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: requester.sender.to_string(),
+                    msg: to_json_binary(&CallbackMsg::OnPayoutTimeout { request_id }).unwrap(),
+                    funds: vec![],
+                },
+                CALLBACK_REPLY_ID,
+            )
+        };
+        let resp = execute(deps.as_mut(), env.clone(), message_info(&keeper, &[]), timeout(2)).unwrap();
+        assert_eq!(resp.messages[..2], [timed_out(&alice, 1), timed_out(&bob, 2)]);
+        assert_eq!(
+            resp.messages[2].msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address: keeper.to_string(), amount: vec![coin(10, "BOUNTY")] })
+        );
+        // bob still waits on request 1
+        let inflight = INFLIGHT_REQUESTS
+            .range(&deps.storage, None, None, cosmwasm_std::Order::Ascending)
+            .map(|item| item.unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(inflight, vec![1]);
+
+        let resp = execute(deps.as_mut(), env.clone(), message_info(&keeper, &[]), timeout(2)).unwrap();
+        assert_eq!(resp.messages, [timed_out(&bob, 1)]);
+        assert!(INFLIGHT_REQUESTS.is_empty(&deps.storage));
+        assert!(PENDING_EXPIRATIONS.is_empty(&deps.storage));
+        assert!(ADAPTER_REQUESTS.is_empty(&deps.storage));
+
+        // abandoned requests are forgotten, so a late answer is refused
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&adapter, &[]),
+            ExecuteMsg::OnPriceResponse { request_id: 1, price: Decimal::one(), price_timestamp: env.block.time.seconds() },
+        ).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownRequest { request_id: 1 }));
+    }
+
+    #[test]
+    fn aggregated_pairs_settle_once_a_quorum_agrees() {
+        use cosmwasm_std::{from_json, Decimal, SubMsg, WasmMsg};
+        use cw_utils::Expiration;
+        use drip_price_oracle_interface::msg::{
+            AdapterAnswer, AggregationConfig, AggregationMode, AggregationOutcome,
+            AggregationRecord, CallbackMsg, PriceKey,
+        };
+        use crate::aggregate::aggregate;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&deps.api.addr_make("admin"), &[]);
+        let adapters = ["a", "b", "c"].map(|name| deps.api.addr_make(name));
+        let disburser = deps.api.addr_make("disburser");
+        let msg = InstantiateMsg { adapters: None, admin: admin.sender.clone(), keeper_bounty: None };
+        instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        let pair = PriceKey { base: "uatom".to_string(), quote: "uusdc".to_string() };
+        let config = |quorum| AggregationConfig {
+            adapters: adapters.to_vec(),
+            quorum,
+            mode: AggregationMode::Median,
+            max_deviation: Decimal::percent(5),
+        };
+        let set = |aggregation| ExecuteMsg::SetAggregation { pair: pair.clone(), aggregation: Some(aggregation) };
+        let err = execute(deps.as_mut(), env.clone(), admin.clone(), set(config(4))).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidAggregation {}));
+        execute(deps.as_mut(), env.clone(), admin.clone(), set(config(2))).unwrap();
+
+        let request = |sequence| ExecuteMsg::RequestPrice {
+            base: "uatom".to_string(),
+            quote: "uusdc".to_string(),
+            expiration: Expiration::AtTime(env.block.time.plus_seconds(60)),
+            valid_from: None,
+            sequence: Some(sequence),
+        };
+        let answer = |request_id, percent, observed: u64| ExecuteMsg::OnPriceResponse {
+            request_id,
+            price: Decimal::percent(percent),
+            price_timestamp: env.block.time.seconds() + observed,
+        };
+        let from = |i: usize| message_info(&adapters[i], &[]);
+        let callback = |msg: &CallbackMsg| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: disburser.to_string(),
+                    msg: to_json_binary(msg).unwrap(),
+                    funds: vec![],
+                },
+                CALLBACK_REPLY_ID,
+            )
+        };
+
+        // every adapter is asked; two far apart answers are no quorum yet
+        let resp = execute(deps.as_mut(), env.clone(), message_info(&disburser, &[]), request(1)).unwrap();
+        assert_eq!(resp.messages.len(), 3);
+        let resp = execute(deps.as_mut(), env.clone(), from(0), answer(1, 100, 1)).unwrap();
+        assert!(resp.messages.is_empty());
+        let err = execute(deps.as_mut(), env.clone(), from(0), answer(1, 100, 1)).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyAnswered { request_id: 1 }));
+        let resp = execute(deps.as_mut(), env.clone(), from(1), answer(1, 200, 2)).unwrap();
+        assert!(resp.messages.is_empty());
+
+        // the third answer makes a quorum with the first, the outlier is dropped
+        let resp = execute(deps.as_mut(), env.clone(), from(2), answer(1, 102, 3)).unwrap();
+        let settled = CallbackMsg::OnPayoutResponse {
+            price: Decimal::percent(101),
+            price_timestamp: env.block.time.seconds() + 1,
+            request_id: 1,
+        };
+        assert_eq!(resp.messages, vec![callback(&settled)]);
+        let record: Option<AggregationRecord> = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::AggregationRecord { request_id: 1 }).unwrap(),
+        ).unwrap();
+        let record = record.unwrap();
+        assert_eq!(record.answers.len(), 3);
+        assert_eq!(
+            record.outcome,
+            Some(AggregationOutcome::Settled {
+                price: Decimal::percent(101),
+                price_timestamp: env.block.time.seconds() + 1,
+                accepted: vec![adapters[0].clone(), adapters[2].clone()],
+            })
+        );
+
+        // without a quorum once all answered, the requesters are timed out
+        execute(deps.as_mut(), env.clone(), admin.clone(), set(config(3))).unwrap();
+        execute(deps.as_mut(), env.clone(), message_info(&disburser, &[]), request(2)).unwrap();
+        execute(deps.as_mut(), env.clone(), from(0), answer(2, 100, 1)).unwrap();
+        execute(deps.as_mut(), env.clone(), from(1), answer(2, 101, 1)).unwrap();
+        let resp = execute(deps.as_mut(), env.clone(), from(2), answer(2, 150, 1)).unwrap();
+        assert_eq!(resp.messages, vec![callback(&CallbackMsg::OnPayoutTimeout { request_id: 2 })]);
+        let err = execute(deps.as_mut(), env.clone(), from(2), answer(2, 150, 1)).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownRequest { request_id: 2 }));
+
+        // a trimmed mean drops the same share of answers at each end
+        let answers = [100, 200, 300, 10_000].map(|percent| AdapterAnswer {
+            adapter: adapters[0].clone(),
+            price: Decimal::percent(percent),
+            price_timestamp: 0,
+        });
+        let trimmed = AggregationConfig {
+            adapters: vec![],
+            quorum: 4,
+            mode: AggregationMode::TrimmedMean { trim: Decimal::percent(25) },
+            max_deviation: Decimal::percent(10_000),
+        };
+        assert!(matches!(
+            aggregate(&trimmed, &answers).unwrap(),
+            Some(AggregationOutcome::Settled { price, .. }) if price == Decimal::percent(250)
+        ));
+    }
+}
@@ -10,4 +10,25 @@ pub enum ContractError {
     Unauthorized {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
+
+    #[error("Invalid keeper bounty: non-zero reward and cap required")]
+    InvalidKeeperBounty {},
+    #[error("No adapter for {base}/{quote}")]
+    UnsupportedPair { base: String, quote: String },
+    #[error("Unknown adapter")]
+    UnknownAdapter {},
+    #[error("Unknown price request {request_id}")]
+    UnknownRequest { request_id: u64 },
+    #[error("Request expiration must be a height or a time")]
+    InvalidExpiration {},
+    #[error("Request has already expired")]
+    RequestExpired {},
+    #[error("No expired request to time out")]
+    NothingExpired {},
+    #[error("Invalid aggregation: unique adapters, a quorum they can reach and a trim below one half required")]
+    InvalidAggregation {},
+    #[error("Adapter already answered request {request_id}")]
+    AlreadyAnswered { request_id: u64 },
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
 }
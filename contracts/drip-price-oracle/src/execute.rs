@@ -0,0 +1,542 @@
+use cosmwasm_std::{
+    ensure, to_json_binary, Addr, BankMsg, Decimal, DepsMut, Env, MessageInfo, Order, Response,
+    StdResult, Storage, SubMsg, WasmMsg,
+};
+use cw_utils::Expiration;
+use drip_price_oracle_interface::msg::{
+    AdapterAnswer, AdapterExecuteMsg, AggregationConfig, AggregationOutcome, AggregationRecord,
+    CallbackMsg, KeeperBounty, PriceKey,
+};
+
+use crate::aggregate::{aggregate, validate_aggregation};
+use crate::bounty::{keeper_reward, validate_keeper_bounty};
+use crate::helpers::{ensure_admin, to_stamp};
+use crate::state::{
+    remove_pending, save_pending, AdapterRequest, CachedPrice, PendingVal, Source, Stamp,
+    StampOne, ADAPTERS, ADAPTER_REQUESTS, AGGREGATIONS, AGGREGATION_RECORDS, CONFIG,
+    EXPIRATION_HEIGHT, EXPIRATION_TIME, INFLIGHT_REQUESTS, INFLIGHT_SEQUENCE,
+    PENDING_EXPIRATIONS, PENDING_REQUESTS, PRICE_CACHE, REWARDED_REQUESTERS,
+};
+use crate::ContractError;
+
+/// Failed requester callbacks are swallowed so that one consumer cannot block the others
+pub const CALLBACK_REPLY_ID: u64 = 1;
+
+/// Answers the requester at once from the cached price of the pair when it was observed
+/// at or after `valid_from`. Otherwise attaches the requester to an adapter request in
+/// flight for the pair when one fits its window, or forwards a new request to the adapter
+/// routed for the pair. The requester is called back with `sequence`, or the request id
+/// when unset.
+///
+/// A request in flight fits when it was sent at or after `valid_from`, or in this block
+/// without it, and its adapter deadline is no later than `expiration` but not passed yet,
+/// so that its answer is both fresh enough and timely for every requester waiting on it.
+/// There is at most one adapter request per pair and block, so one sent in this block
+/// always fits; a requester with an earlier deadline then simply times out on its own.
+/// Requesters without `valid_from` therefore only share adapter requests within a block.
+#[allow(clippy::too_many_arguments)]
+pub fn request_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    base: String,
+    quote: String,
+    expiration: Expiration,
+    valid_from: Option<Expiration>,
+    sequence: Option<u64>,
+) -> Result<Response, ContractError> {
+    let source = match AGGREGATIONS.may_load(deps.storage, (&base, &quote))? {
+        Some(aggregation) => Source::Aggregated(aggregation),
+        None => ADAPTERS
+            .may_load(deps.storage, (&base, &quote))?
+            .map(Source::Adapter)
+            .ok_or_else(|| ContractError::UnsupportedPair {
+                base: base.clone(),
+                quote: quote.clone(),
+            })?,
+    };
+    ensure!(!expiration.is_expired(&env.block), ContractError::RequestExpired {});
+    let expires = to_stamp(&expiration)?;
+    let window_start = match &valid_from {
+        Some(from) => to_stamp(from)?,
+        None => StampOne::Timestamp(env.block.time.seconds()),
+    };
+    let price_key = PriceKey {
+        base: base.clone(),
+        quote: quote.clone(),
+    };
+
+    let mut resp = Response::new().add_attribute("action", "request_price");
+    if valid_from.is_some() {
+        let cached = PRICE_CACHE.may_load(deps.storage, (&base, &quote))?;
+        if let Some(cached) = cached.filter(|c| c.observed_at().reached(&window_start)) {
+            let request_id = next_request_id(deps.storage)?;
+            let sequence = sequence.unwrap_or(request_id);
+            let msg = callback(
+                &info.sender,
+                &CallbackMsg::OnPayoutResponse {
+                    price: cached.price,
+                    price_timestamp: cached.price_timestamp,
+                    request_id: sequence,
+                },
+            )?;
+            return Ok(resp
+                .add_submessage(msg)
+                .add_attribute("cached", "true")
+                .add_attribute("request_id", request_id.to_string())
+                .add_attribute("sequence", sequence.to_string()));
+        }
+    }
+
+    let (request_id, sent_at) = match find_inflight(deps.storage, &env, &price_key, &window_start, &expires)? {
+        Some(inflight) => {
+            resp = resp.add_attribute("coalesced", "true");
+            inflight
+        }
+        None => {
+            let request_id = next_request_id(deps.storage)?;
+            let request = AdapterRequest {
+                source,
+                price_key: price_key.clone(),
+                expiration: expires.clone(),
+                sent_at: Stamp {
+                    timestamp: env.block.time.seconds(),
+                    block_height: env.block.height,
+                },
+            };
+            ADAPTER_REQUESTS.save(deps.storage, request_id, &request)?;
+            INFLIGHT_REQUESTS.save(deps.storage, request.inflight_key(), &request_id)?;
+            if let Source::Aggregated(_) = &request.source {
+                AGGREGATION_RECORDS.save(
+                    deps.storage,
+                    request_id,
+                    &AggregationRecord {
+                        pair: price_key.clone(),
+                        answers: vec![],
+                        outcome: None,
+                    },
+                )?;
+            }
+
+            let msg = to_json_binary(&AdapterExecuteMsg::RequestPrice {
+                base,
+                quote,
+                sequence: request_id,
+                valid_from,
+                expiration,
+            })?;
+            for adapter in request.source.adapters() {
+                resp = resp
+                    .add_message(WasmMsg::Execute {
+                        contract_addr: adapter.to_string(),
+                        msg: msg.clone(),
+                        funds: vec![],
+                    })
+                    .add_attribute("adapter", adapter);
+            }
+            (request_id, request.sent_at)
+        }
+    };
+
+    let sequence = sequence.unwrap_or(request_id);
+    save_pending(
+        deps.storage,
+        (request_id, info.sender, sequence),
+        &PendingVal {
+            price_key,
+            expiration: expires,
+            window_start,
+            inflight_request_timestamp: sent_at,
+        },
+    )?;
+
+    Ok(resp
+        .add_attribute("request_id", request_id.to_string())
+        .add_attribute("sequence", sequence.to_string()))
+}
+
+fn next_request_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let request_id = INFLIGHT_SEQUENCE.may_load(storage)?.unwrap_or_default() + 1;
+    INFLIGHT_SEQUENCE.save(storage, &request_id)?;
+    Ok(request_id)
+}
+
+/// Latest adapter request in flight for `pair` fitting the window of a new request.
+fn find_inflight(
+    storage: &dyn Storage,
+    env: &Env,
+    pair: &PriceKey,
+    window_start: &StampOne,
+    expiration: &StampOne,
+) -> StdResult<Option<(u64, Stamp)>> {
+    let inflight = INFLIGHT_REQUESTS
+        .sub_prefix((pair.base.clone(), pair.quote.clone()))
+        .range(storage, None, None, Order::Descending);
+    for item in inflight {
+        let ((timestamp, block_height), request_id) = item?;
+        let sent_at = Stamp {
+            timestamp,
+            block_height,
+        };
+        // the rest was sent even earlier
+        if !sent_at.reached(window_start) {
+            break;
+        }
+        let request = ADAPTER_REQUESTS.load(storage, request_id)?;
+        if request.expiration.is_expired(&env.block) {
+            continue;
+        }
+        if block_height == env.block.height || request.expiration.not_after(expiration) {
+            return Ok(Some((request_id, sent_at)));
+        }
+    }
+    Ok(None)
+}
+
+/// Resolves request `request_id` with the price of its adapter and fans it out to every
+/// requester waiting on it, each called back with its own sequence. The price replaces
+/// the cached one of the pair unless that was observed later.
+///
+/// An aggregated request records the answer and settles with the aggregate once a quorum
+/// of answers lies within the deviation, or times its requesters out when every adapter
+/// answered without one. It is forgotten once every adapter has answered.
+pub fn on_price_response(
+    deps: DepsMut,
+    info: MessageInfo,
+    request_id: u64,
+    price: Decimal,
+    price_timestamp: u64,
+) -> Result<Response, ContractError> {
+    let request = ADAPTER_REQUESTS
+        .may_load(deps.storage, request_id)?
+        .ok_or(ContractError::UnknownRequest { request_id })?;
+    ensure!(
+        request.source.adapters().contains(&info.sender),
+        ContractError::Unauthorized {}
+    );
+    let mut resp = Response::new()
+        .add_attribute("action", "on_price_response")
+        .add_attribute("request_id", request_id.to_string())
+        .add_attribute("base", request.price_key.base.clone())
+        .add_attribute("quote", request.price_key.quote.clone());
+
+    let Source::Aggregated(aggregation) = &request.source else {
+        ADAPTER_REQUESTS.remove(deps.storage, request_id);
+        INFLIGHT_REQUESTS.remove(deps.storage, request.inflight_key());
+        let callbacks = settle(deps.storage, request_id, &request, price, price_timestamp)?;
+        return Ok(resp
+            .add_submessages(callbacks)
+            .add_attribute("price", price.to_string()));
+    };
+
+    let mut record = AGGREGATION_RECORDS.load(deps.storage, request_id)?;
+    ensure!(
+        record.answers.iter().all(|a| a.adapter != info.sender),
+        ContractError::AlreadyAnswered { request_id }
+    );
+    record.answers.push(AdapterAnswer {
+        adapter: info.sender,
+        price,
+        price_timestamp,
+    });
+    let all_answered = record.answers.len() == aggregation.adapters.len();
+    if all_answered {
+        ADAPTER_REQUESTS.remove(deps.storage, request_id);
+    }
+    resp = resp.add_attribute("answers", record.answers.len().to_string());
+
+    if record.outcome.is_none() {
+        let outcome = aggregate(aggregation, &record.answers)?
+            .or_else(|| all_answered.then_some(AggregationOutcome::NoQuorum {}));
+        if let Some(outcome) = &outcome {
+            INFLIGHT_REQUESTS.remove(deps.storage, request.inflight_key());
+            let callbacks = match outcome {
+                AggregationOutcome::Settled {
+                    price,
+                    price_timestamp,
+                    ..
+                } => {
+                    resp = resp.add_attribute("price", price.to_string());
+                    settle(deps.storage, request_id, &request, *price, *price_timestamp)?
+                }
+                AggregationOutcome::NoQuorum {} => {
+                    resp = resp.add_attribute("outcome", "no_quorum");
+                    call_back_waiting(deps.storage, request_id, |request_id| {
+                        CallbackMsg::OnPayoutTimeout { request_id }
+                    })?
+                }
+            };
+            resp = resp.add_submessages(callbacks);
+        }
+        record.outcome = outcome;
+    }
+    AGGREGATION_RECORDS.save(deps.storage, request_id, &record)?;
+
+    Ok(resp)
+}
+
+/// Caches the price of `request` and answers its requesters with it
+fn settle(
+    storage: &mut dyn Storage,
+    request_id: u64,
+    request: &AdapterRequest,
+    price: Decimal,
+    price_timestamp: u64,
+) -> StdResult<Vec<SubMsg>> {
+    let pair = (request.price_key.base.as_str(), request.price_key.quote.as_str());
+    let cached = PRICE_CACHE.may_load(storage, pair)?;
+    if cached.is_none_or(|c| c.price_timestamp <= price_timestamp) {
+        PRICE_CACHE.save(
+            storage,
+            pair,
+            &CachedPrice {
+                price,
+                price_timestamp,
+                block_height: request.sent_at.block_height,
+            },
+        )?;
+    }
+
+    call_back_waiting(storage, request_id, |request_id| CallbackMsg::OnPayoutResponse {
+        price,
+        price_timestamp,
+        request_id,
+    })
+}
+
+/// Removes every requester waiting on `request_id`, calling each back with its sequence
+fn call_back_waiting(
+    storage: &mut dyn Storage,
+    request_id: u64,
+    msg: impl Fn(u64) -> CallbackMsg,
+) -> StdResult<Vec<SubMsg>> {
+    let waiting = PENDING_REQUESTS
+        .sub_prefix(request_id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut callbacks = Vec::with_capacity(waiting.len());
+    for (requester, sequence) in waiting {
+        remove_pending(storage, (request_id, requester.clone(), sequence))?;
+        callbacks.push(callback(&requester, &msg(sequence))?);
+    }
+    Ok(callbacks)
+}
+
+/// Calls back up to `batch_limit` expired pending requests with a timeout, the earliest
+/// first, block heights before times. Adapter requests left without requesters are
+/// forgotten along with their aggregation record, so a late answer is refused as
+/// unknown. Callable by anyone, fails when nothing has expired.
+///
+/// The caller earns one keeper bounty unit per request of a rewarded requester timed out.
+pub fn timeout_expired_requests(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    batch_limit: u64,
+) -> Result<Response, ContractError> {
+    let mut expired = Vec::new();
+    for (unit, now) in [
+        (EXPIRATION_HEIGHT, env.block.height),
+        (EXPIRATION_TIME, env.block.time.seconds()),
+    ] {
+        let remaining = (batch_limit as usize).saturating_sub(expired.len());
+        let keys = PENDING_EXPIRATIONS
+            .sub_prefix(unit)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .take_while(|key| !matches!(key, Ok((at, _)) if *at > now))
+            .take(remaining)
+            .map(|key| key.map(|(_, pending)| pending))
+            .collect::<StdResult<Vec<_>>>()?;
+        expired.extend(keys);
+    }
+    ensure!(!expired.is_empty(), ContractError::NothingExpired {});
+
+    let mut resp = Response::new().add_attribute("action", "timeout_expired_requests");
+    for (request_id, requester, sequence) in &expired {
+        remove_pending(deps.storage, (*request_id, requester.clone(), *sequence))?;
+        resp = resp.add_submessage(callback(
+            requester,
+            &CallbackMsg::OnPayoutTimeout {
+                request_id: *sequence,
+            },
+        )?);
+    }
+
+    let mut request_ids = expired.iter().map(|(id, _, _)| *id).collect::<Vec<_>>();
+    request_ids.sort_unstable();
+    request_ids.dedup();
+    for request_id in request_ids {
+        let waiting = PENDING_REQUESTS
+            .sub_prefix(request_id)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .next();
+        if waiting.is_some() {
+            continue;
+        }
+        if let Some(request) = ADAPTER_REQUESTS.may_load(deps.storage, request_id)? {
+            INFLIGHT_REQUESTS.remove(deps.storage, request.inflight_key());
+            ADAPTER_REQUESTS.remove(deps.storage, request_id);
+            AGGREGATION_RECORDS.remove(deps.storage, request_id);
+            resp = resp.add_attribute("abandoned_request_id", request_id.to_string());
+        }
+    }
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let rewarded = expired
+        .iter()
+        .filter(|(_, requester, _)| REWARDED_REQUESTERS.has(deps.storage, requester))
+        .count();
+    let units = u32::try_from(rewarded).unwrap_or(u32::MAX);
+    if let Some(reward) = keeper_reward(deps.storage, &deps.querier, &env, cfg.keeper_bounty.as_ref(), units)? {
+        resp = resp
+            .add_attribute("keeper_reward", reward.to_string())
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![reward],
+            });
+    }
+
+    Ok(resp.add_attribute("timed_out", expired.len().to_string()))
+}
+
+pub(crate) fn callback(requester: &Addr, msg: &CallbackMsg) -> StdResult<SubMsg> {
+    let msg = WasmMsg::Execute {
+        contract_addr: requester.to_string(),
+        msg: to_json_binary(msg)?,
+        funds: vec![],
+    };
+    Ok(SubMsg::reply_on_error(msg, CALLBACK_REPLY_ID))
+}
+
+pub fn add_adapter(
+    deps: DepsMut,
+    info: MessageInfo,
+    adapter: Addr,
+    pairs: Vec<PriceKey>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+
+    let adapter = deps.api.addr_validate(adapter.as_str())?;
+    let count = pairs.len();
+    for pair in pairs {
+        ADAPTERS.save(deps.storage, (&pair.base, &pair.quote), &adapter)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "add_adapter")
+        .add_attribute("adapter", adapter)
+        .add_attribute("pairs", count.to_string()))
+}
+
+pub fn remove_adapter(
+    deps: DepsMut,
+    info: MessageInfo,
+    adapter: Addr,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+
+    let pairs = ADAPTERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, a)) if *a != adapter))
+        .map(|item| item.map(|(pair, _)| pair))
+        .collect::<StdResult<Vec<_>>>()?;
+    ensure!(!pairs.is_empty(), ContractError::UnknownAdapter {});
+    for (base, quote) in &pairs {
+        ADAPTERS.remove(deps.storage, (base, quote));
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_adapter")
+        .add_attribute("adapter", adapter)
+        .add_attribute("pairs", pairs.len().to_string()))
+}
+
+pub fn set_aggregation(
+    deps: DepsMut,
+    info: MessageInfo,
+    pair: PriceKey,
+    aggregation: Option<AggregationConfig>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+
+    let key = (pair.base.as_str(), pair.quote.as_str());
+    let resp = Response::new()
+        .add_attribute("action", "set_aggregation")
+        .add_attribute("base", &pair.base)
+        .add_attribute("quote", &pair.quote);
+    let Some(mut aggregation) = aggregation else {
+        AGGREGATIONS.remove(deps.storage, key);
+        return Ok(resp.add_attribute("adapters", "0"));
+    };
+    aggregation.adapters = aggregation
+        .adapters
+        .iter()
+        .map(|adapter| deps.api.addr_validate(adapter.as_str()))
+        .collect::<StdResult<_>>()?;
+    validate_aggregation(&aggregation)?;
+    AGGREGATIONS.save(deps.storage, key, &aggregation)?;
+
+    Ok(resp.add_attribute("adapters", aggregation.adapters.len().to_string()))
+}
+
+pub fn update_admin(
+    deps: DepsMut,
+    info: MessageInfo,
+    admin: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+
+    cfg.admin = admin.map(|a| deps.api.addr_validate(&a)).transpose()?;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_admin")
+        .add_attribute(
+            "admin",
+            cfg.admin.map(String::from).unwrap_or_else(|| "none".to_string()),
+        ))
+}
+
+pub fn update_keeper_bounty(
+    deps: DepsMut,
+    info: MessageInfo,
+    keeper_bounty: Option<KeeperBounty>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+
+    if let Some(bounty) = &keeper_bounty {
+        validate_keeper_bounty(bounty)?;
+    }
+    cfg.keeper_bounty = keeper_bounty;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    Ok(Response::new().add_attribute("action", "update_keeper_bounty"))
+}
+
+pub fn update_rewarded_requesters(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    ensure_admin(&cfg, &info.sender)?;
+
+    for requester in &add {
+        REWARDED_REQUESTERS.save(deps.storage, &deps.api.addr_validate(requester)?, &())?;
+    }
+    for requester in &remove {
+        REWARDED_REQUESTERS.remove(deps.storage, &deps.api.addr_validate(requester)?);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_rewarded_requesters")
+        .add_attribute("added", add.len().to_string())
+        .add_attribute("removed", remove.len().to_string()))
+}
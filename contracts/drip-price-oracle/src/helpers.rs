@@ -1,10 +1,14 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{to_json_binary, Addr, CosmosMsg, StdResult, WasmMsg};
+use cosmwasm_std::{ensure, to_json_binary, Addr, CosmosMsg, StdResult, WasmMsg};
+use cw_utils::Expiration;
 
 use drip_price_oracle_interface::msg::ExecuteMsg;
 
+use crate::state::{Config, StampOne};
+use crate::ContractError;
+
 /// CwTemplateContract is a wrapper around Addr that provides a lot of helpers
 /// for working with this.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -25,3 +29,20 @@ impl CwTemplateContract {
             .into())
     }
 }
+
+pub fn ensure_admin(cfg: &Config, sender: &Addr) -> Result<(), ContractError> {
+    ensure!(
+        cfg.admin.as_ref() == Some(sender),
+        ContractError::Unauthorized {}
+    );
+    Ok(())
+}
+
+/// Height or time, in seconds, of a request deadline; `Never` is refused
+pub fn to_stamp(at: &Expiration) -> Result<StampOne, ContractError> {
+    match at {
+        Expiration::AtHeight(height) => Ok(StampOne::BlockHeight(*height)),
+        Expiration::AtTime(time) => Ok(StampOne::Timestamp(time.seconds())),
+        Expiration::Never {} => Err(ContractError::InvalidExpiration {}),
+    }
+}
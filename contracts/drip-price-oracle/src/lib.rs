@@ -1,6 +1,10 @@
+pub mod aggregate;
+pub mod bounty;
 pub mod contract;
 mod error;
+pub mod execute;
 pub mod helpers;
+pub mod query;
 pub mod state;
 
 pub use crate::error::ContractError;
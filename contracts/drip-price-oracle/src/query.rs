@@ -0,0 +1,114 @@
+use cosmwasm_std::{Addr, Deps, Order, StdResult};
+use cw_storage_plus::Bound;
+use drip_price_oracle_interface::msg::{
+    AdapterRoute, AdaptersResponse, AggregationConfig, AggregationRecord, CachedPriceResponse, CachedPricesResponse, ConfigResponse,
+    PriceKey, RewardedRequestersResponse,
+};
+
+use crate::state::{
+    CachedPrice, ADAPTERS, AGGREGATIONS, AGGREGATION_RECORDS, CONFIG, PRICE_CACHE,
+    REWARDED_REQUESTERS,
+};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        admin: cfg.admin,
+        keeper_bounty: cfg.keeper_bounty,
+    })
+}
+
+pub fn adapter(deps: Deps, base: String, quote: String) -> StdResult<Addr> {
+    ADAPTERS.load(deps.storage, (&base, &quote))
+}
+
+pub fn adapters(
+    deps: Deps,
+    start_after: Option<PriceKey>,
+    limit: Option<u32>,
+) -> StdResult<AdaptersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .as_ref()
+        .map(|pair| Bound::exclusive((pair.base.as_str(), pair.quote.as_str())));
+
+    let routes = ADAPTERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|((base, quote), adapter)| AdapterRoute {
+                pair: PriceKey { base, quote },
+                adapter,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(AdaptersResponse { routes })
+}
+
+pub fn aggregation(deps: Deps, base: String, quote: String) -> StdResult<Option<AggregationConfig>> {
+    AGGREGATIONS.may_load(deps.storage, (&base, &quote))
+}
+
+pub fn aggregation_record(deps: Deps, request_id: u64) -> StdResult<Option<AggregationRecord>> {
+    AGGREGATION_RECORDS.may_load(deps.storage, request_id)
+}
+
+pub fn cached_price(
+    deps: Deps,
+    base: String,
+    quote: String,
+) -> StdResult<Option<CachedPriceResponse>> {
+    let cached = PRICE_CACHE.may_load(deps.storage, (&base, &quote))?;
+    Ok(cached.map(|cached| cached_price_response(PriceKey { base, quote }, cached)))
+}
+
+pub fn cached_prices(
+    deps: Deps,
+    start_after: Option<PriceKey>,
+    limit: Option<u32>,
+) -> StdResult<CachedPricesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .as_ref()
+        .map(|pair| Bound::exclusive((pair.base.as_str(), pair.quote.as_str())));
+
+    let prices = PRICE_CACHE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|((base, quote), cached)| {
+                cached_price_response(PriceKey { base, quote }, cached)
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(CachedPricesResponse { prices })
+}
+
+pub fn rewarded_requesters(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<RewardedRequestersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .map(|a| deps.api.addr_validate(&a))
+        .transpose()?;
+
+    let requesters = REWARDED_REQUESTERS
+        .keys(deps.storage, start_after.as_ref().map(Bound::exclusive), None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(RewardedRequestersResponse { requesters })
+}
+
+fn cached_price_response(pair: PriceKey, cached: CachedPrice) -> CachedPriceResponse {
+    CachedPriceResponse {
+        pair,
+        price: cached.price,
+        price_timestamp: cached.price_timestamp,
+        block_height: cached.block_height,
+    }
+}
@@ -1,31 +1,102 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, BlockInfo, Decimal, StdResult, Storage};
 use cw_storage_plus::{Item, Map};
-use cw_utils::Expiration;
+pub use drip_price_oracle_interface::msg::PriceKey;
+use drip_price_oracle_interface::msg::{AggregationConfig, AggregationRecord, KeeperBounty};
 
 #[cw_serde]
-pub struct Config {}
+pub struct Config {
+    /// Manages the adapter registry, `None` once renounced
+    pub admin: Option<Addr>,
+    pub keeper_bounty: Option<KeeperBounty>,
+}
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// Requesters whose expired requests earn the keeper bounty
+pub const REWARDED_REQUESTERS: Map<&Addr, ()> = Map::new("rewarded_requesters");
+
+/// Adapter serving each `(base, quote)` pair
+pub const ADAPTERS: Map<(&str, &str), Addr> = Map::new("adapters");
+
+/// Pairs aggregated over several adapters instead of their entry in `ADAPTERS`
+pub const AGGREGATIONS: Map<(&str, &str), AggregationConfig> = Map::new("aggregations");
+
+/// Audit of every aggregated request, kept after it is resolved
+pub const AGGREGATION_RECORDS: Map<RequestId, AggregationRecord> =
+    Map::new("aggregation_records");
+
+/// Latest price received for a pair
 #[cw_serde]
-pub struct PriceKey {
-    pub base: String,
-    pub quote: String,
+pub struct CachedPrice {
+    pub price: Decimal,
+    /// When the adapter observed the price, in seconds
+    pub price_timestamp: u64,
+    /// Height the answered adapter request was sent at; the price is no older than that
+    pub block_height: u64,
+}
+
+impl CachedPrice {
+    pub fn observed_at(&self) -> Stamp {
+        Stamp {
+            timestamp: self.price_timestamp,
+            block_height: self.block_height,
+        }
+    }
 }
 
+pub const PRICE_CACHE: Map<(&str, &str), CachedPrice> = Map::new("price_cache");
+
 #[cw_serde]
 pub struct Stamp {
     pub timestamp: u64,
     pub block_height: u64,
 }
 
+impl Stamp {
+    /// Whether this moment is at or after `at`
+    pub fn reached(&self, at: &StampOne) -> bool {
+        match at {
+            StampOne::Timestamp(t) => self.timestamp >= *t,
+            StampOne::BlockHeight(h) => self.block_height >= *h,
+        }
+    }
+}
+
+/// Time in seconds or block height
 #[cw_serde]
 pub enum StampOne {
     Timestamp(u64),
     BlockHeight(u64),
 }
 
+impl StampOne {
+    /// Whether this comes no later than `other`; stamps of different units never compare.
+    pub fn not_after(&self, other: &StampOne) -> bool {
+        match (self, other) {
+            (StampOne::Timestamp(a), StampOne::Timestamp(b)) => a <= b,
+            (StampOne::BlockHeight(a), StampOne::BlockHeight(b)) => a <= b,
+            _ => false,
+        }
+    }
+
+    /// Whether `block` reached this stamp, as `Expiration::is_expired` does
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        match self {
+            StampOne::Timestamp(t) => block.time.seconds() >= *t,
+            StampOne::BlockHeight(h) => block.height >= *h,
+        }
+    }
+
+    /// Unit and value ordering the stamps of one unit by time
+    pub fn index_key(&self) -> (u8, u64) {
+        match self {
+            StampOne::BlockHeight(h) => (EXPIRATION_HEIGHT, *h),
+            StampOne::Timestamp(t) => (EXPIRATION_TIME, *t),
+        }
+    }
+}
+
 #[cw_serde]
 pub struct PendingVal {
     pub price_key: PriceKey,
@@ -38,13 +109,81 @@ type Base = String;
 type Quote = String;
 type RequestTimestamp = u64;
 type RequestBlockHeight = u64;
-type InflightKey = (Base, Quote, RequestTimestamp, RequestBlockHeight);
+/// The pair is nested so that the in-flight requests of a pair share a prefix
+type InflightKey = ((Base, Quote), RequestTimestamp, RequestBlockHeight);
 type RequestId = u64; // based on inflight_sequence
 type UserSequence = u64;
 
 pub const INFLIGHT_SEQUENCE: Item<u64> = Item::new("inflight_sequence");
 
+/// Adapter requests in flight per pair, by the time they were sent
 pub const INFLIGHT_REQUESTS: Map<InflightKey, RequestId> = Map::new("inflight_requests");
 
-pub const PENDING_REQUESTS: Map<(RequestId, Addr, UserSequence), PendingVal> =
-    Map::new("pending_requests");
+type PendingKey = (RequestId, Addr, UserSequence);
+type ExpirationUnit = u8;
+type ExpiresAt = u64;
+
+pub const EXPIRATION_HEIGHT: ExpirationUnit = 0;
+pub const EXPIRATION_TIME: ExpirationUnit = 1;
+
+/// Written through `save_pending` and `remove_pending` only, which keep
+/// `PENDING_EXPIRATIONS` in sync
+pub const PENDING_REQUESTS: Map<PendingKey, PendingVal> = Map::new("pending_requests");
+
+/// Pending requests by expiration, in order within each unit
+pub const PENDING_EXPIRATIONS: Map<(ExpirationUnit, ExpiresAt, PendingKey), ()> =
+    Map::new("pending_expirations");
+
+pub fn save_pending(storage: &mut dyn Storage, key: PendingKey, val: &PendingVal) -> StdResult<()> {
+    remove_pending(storage, key.clone())?;
+    let (unit, at) = val.expiration.index_key();
+    PENDING_EXPIRATIONS.save(storage, (unit, at, key.clone()), &())?;
+    PENDING_REQUESTS.save(storage, key, val)
+}
+
+pub fn remove_pending(storage: &mut dyn Storage, key: PendingKey) -> StdResult<()> {
+    if let Some(val) = PENDING_REQUESTS.may_load(storage, key.clone())? {
+        let (unit, at) = val.expiration.index_key();
+        PENDING_EXPIRATIONS.remove(storage, (unit, at, key.clone()));
+        PENDING_REQUESTS.remove(storage, key);
+    }
+    Ok(())
+}
+
+/// Where a request was forwarded, the aggregation as configured when it was sent
+#[cw_serde]
+pub enum Source {
+    Adapter(Addr),
+    Aggregated(AggregationConfig),
+}
+
+impl Source {
+    pub fn adapters(&self) -> &[Addr] {
+        match self {
+            Source::Adapter(adapter) => std::slice::from_ref(adapter),
+            Source::Aggregated(aggregation) => &aggregation.adapters,
+        }
+    }
+}
+
+/// Request forwarded to one or more adapters, answered by `OnPriceResponse`
+#[cw_serde]
+pub struct AdapterRequest {
+    pub source: Source,
+    pub price_key: PriceKey,
+    /// Deadline passed to the adapter, that of the requester who started it
+    pub expiration: StampOne,
+    pub sent_at: Stamp,
+}
+
+impl AdapterRequest {
+    pub fn inflight_key(&self) -> InflightKey {
+        (
+            (self.price_key.base.clone(), self.price_key.quote.clone()),
+            self.sent_at.timestamp,
+            self.sent_at.block_height,
+        )
+    }
+}
+
+pub const ADAPTER_REQUESTS: Map<RequestId, AdapterRequest> = Map::new("adapter_requests");
@@ -0,0 +1,74 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{coin, Coin, Env, QuerierWrapper, StdResult, Storage, Uint128};
+use cw_storage_plus::Item;
+
+/// Reward for permissionless maintenance, paid in `reward.denom` out of the contract's
+/// balance of that denom, which acts as the bounty pool
+#[cw_serde]
+pub struct KeeperBounty {
+    /// Paid per unit of work
+    pub reward: Coin,
+    /// Units rewarded per block across all callers; work beyond it goes unrewarded
+    pub max_units_per_block: u32,
+}
+
+impl KeeperBounty {
+    /// Whether the reward and the per-block cap are both non-zero
+    pub fn is_valid(&self) -> bool {
+        !self.reward.amount.is_zero() && self.max_units_per_block > 0
+    }
+}
+
+/// Keeper reward units paid at a block height, reset on the first reward of a new block
+#[cw_serde]
+#[derive(Default)]
+pub struct KeeperUnits {
+    pub height: u64,
+    pub units: u32,
+}
+
+pub const KEEPER_UNITS: Item<KeeperUnits> = Item::new("keeper_units");
+
+/// Reward for `units` of maintenance done in this block, trimmed to what is left of the
+/// per-block cap and of the pool, and counted against the cap. `None` when there is no
+/// bounty or nothing to pay.
+pub fn keeper_reward(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    bounty: Option<&KeeperBounty>,
+    units: u32,
+) -> StdResult<Option<Coin>> {
+    let Some(bounty) = bounty else {
+        return Ok(None);
+    };
+
+    let mut paid = KEEPER_UNITS.may_load(storage)?.unwrap_or_default();
+    if paid.height != env.block.height {
+        paid = KeeperUnits {
+            height: env.block.height,
+            units: 0,
+        };
+    }
+    let units = units.min(bounty.max_units_per_block.saturating_sub(paid.units));
+    if units == 0 {
+        return Ok(None);
+    }
+
+    let pool = querier
+        .query_balance(&env.contract.address, &bounty.reward.denom)?
+        .amount;
+    let amount = bounty
+        .reward
+        .amount
+        .saturating_mul(Uint128::from(units))
+        .min(pool);
+    if amount.is_zero() {
+        return Ok(None);
+    }
+
+    paid.units += units;
+    KEEPER_UNITS.save(storage, &paid)?;
+
+    Ok(Some(coin(amount.u128(), &bounty.reward.denom)))
+}
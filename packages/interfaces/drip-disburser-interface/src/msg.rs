@@ -1,6 +1,7 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, Uint128, Decimal};
-use cw_utils::Expiration;
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw_utils::{Expiration, Scheduled};
 
 #[cw_serde]
 pub enum Duration {
@@ -22,43 +23,394 @@ pub struct DurationBounds {
     pub min: Option<DurationLimit>,
 }
 
+/// Acceptable oracle price range (settlement asset priced in the quote asset), bounds inclusive
+#[cw_serde]
+#[derive(Default)]
+pub struct PriceBand {
+    pub min: Option<Decimal>,
+    pub max: Option<Decimal>,
+}
+
+/// Recurring payout of a fixed quote amount, requested through `Crank`.
+/// `start` and `end` use the unit of `interval`: a height for blocks, a time for seconds.
+#[cw_serde]
+pub struct Schedule {
+    pub amount_in_quote: Uint128,
+    pub interval: Duration,
+    /// First period is due at `start`
+    pub start: Scheduled,
+    /// No period starting at or after `end` is paid
+    pub end: Option<Scheduled>,
+    pub missed_periods: MissedPeriods,
+}
+
+/// What a crank does about periods that passed without one
+#[cw_serde]
+pub enum MissedPeriods {
+    /// Pays all missed periods in one payout
+    Accumulate,
+    /// Pays a single period and drops the missed ones
+    Skip,
+}
+
+/// Token paid out to recipients: a native denom or a CW20 contract
+#[cw_serde]
+pub enum SettlementAsset {
+    Native { denom: String },
+    Cw20 { contract_addr: String },
+}
+
+pub use drip_keeper_bounty::KeeperBounty;
+
+/// Retries of payouts whose oracle price request timed out
+#[cw_serde]
+pub struct RetryPolicy {
+    /// Price requests sent per payout, the first one included
+    pub max_attempts: u32,
+    /// Wait before a retry is sent by `Crank`; a zero backoff retries right away
+    pub backoff: Duration,
+}
+
+/// Share of every settled payout, in basis points of the settlement amount
+#[cw_serde]
+pub struct RecipientWeight {
+    pub addr: String,
+    pub weight_bps: u16,
+    /// ICS-20 channel to a remote chain; `addr` is then a bech32 address on that chain
+    pub channel_id: Option<String>,
+}
+
 #[cw_serde]
 pub struct InstantiateMsg {
-    pub settlement_asset_limit: Coin,
+    pub settlement_asset: SettlementAsset,
+    pub settlement_asset_limit: Uint128,
     pub quote_asset_limit: Coin,
     pub admin: Option<String>,
+    /// Receives the unspent funds on termination, defaults to the admin
+    pub refund_addr: Option<String>,
+    /// Sole recipient unless `recipients` is set, otherwise receives the rounding dust
     pub recipient_addr: String,
+    /// Weights must add up to 10000 bps
+    pub recipients: Option<Vec<RecipientWeight>>,
     pub oracle_addr: String,
+    /// Initiators without allowance, expiry or per-request limits
     pub payment_initiator_addrs: Vec<String>,
     pub funding_expiration: Expiration,
     pub payout_duration_bounds: DurationBounds,
+    /// Global price band applied to every payout on top of the per-request limits
+    pub price_band: Option<PriceBand>,
+    /// Oldest acceptable oracle price at settlement, in seconds
+    pub max_price_age: Option<u64>,
+    /// Accepts oracle prices observed up to this many seconds before the request, which
+    /// the oracle may answer from its cache right away. A fresh price is requested when unset.
+    pub cached_price_age: Option<u64>,
+    /// Decimal exponent of the quote asset, read from the bank denom metadata when unset
+    pub quote_decimals: Option<u32>,
+    /// Decimal exponent of the settlement asset, read from the bank denom metadata or
+    /// the CW20 token info when unset
+    pub settlement_decimals: Option<u32>,
+    /// Timeout of ICS-20 payouts to remote recipients in seconds, defaults to 10 minutes
+    pub ibc_transfer_timeout: Option<u64>,
+    /// Rewards `Crank` callers per price request sent and `Sweep` callers, none when unset
+    pub keeper_bounty: Option<KeeperBounty>,
+    /// A payout whose price request times out is closed right away when unset
+    pub retry_policy: Option<RetryPolicy>,
+    /// Credited with the native settlement tokens sent along, defaults to the sender
+    pub funder: Option<String>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
+    /// Deposits native settlement tokens, recorded per funder for pro-rata refunds.
+    /// Refunds never exceed the deposits; at most 64 distinct accounts can fund.
+    Fund {},
+    /// CW20 settlement token deposits, carrying a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
     RequestPayout {
         amount_in_quote: Option<Uint128>,
         duration_limit: Option<Duration>,
+        /// Cancels the caller's own pending payouts before queueing this one
         replace_pending: Option<bool>,
+        /// The payout fails instead of settling when the oracle price is below `min_price`
+        min_price: Option<Decimal>,
+        /// The payout fails instead of settling when the oracle price is above `max_price`
+        max_price: Option<Decimal>,
     },
     OnPayoutResponse {
         price: Decimal,
+        /// When the price was observed, in seconds since the Unix epoch
+        price_timestamp: u64,
         request_id: u64,
     },
+    /// The oracle gave up on the price request of a payout: it is retried under the
+    /// retry policy, otherwise closed as timed out.
     OnPayoutTimeout {
         request_id: u64,
     },
     Terminate {},
+    Pause {},
+    Resume {},
+    /// Permissionless: once `funding_expiration` has passed, marks the disburser
+    /// expired and returns the leftover funds to the refund address.
+    Sweep {},
+    /// Cancels the pending payout `expected_seq`, or all of the caller's own pending
+    /// payouts when unset. Requesters cancel their own payouts, the admin any.
     CancelPendingPayout {
         expected_seq: Option<u64>,
     },
     UpdateAdmin { admin: Option<String> },
-    AddPaymentInitiator { addr: String },
+    UpdateRecipients {
+        recipients: Vec<RecipientWeight>,
+        dust_recipient: Option<String>,
+    },
+    /// Adds an initiator or replaces the limits of an existing one.
+    /// Amounts are quote-denominated; `None` means unlimited.
+    AddPaymentInitiator {
+        addr: String,
+        allowance: Option<Uint128>,
+        expires_at: Option<Expiration>,
+        max_per_request: Option<Uint128>,
+    },
     RemovePaymentInitiator { addr: String },
     UpdateWithdrawalTtl { ttl: DurationBounds },
     UpdatePriceFeeder { addr: String },
+    UpdatePriceBand { price_band: Option<PriceBand> },
+    UpdateCachedPriceAge { cached_price_age: Option<u64> },
+    /// Replaces the keeper bounty, `None` stops rewarding keepers.
+    UpdateKeeperBounty { keeper_bounty: Option<KeeperBounty> },
+    UpdateRetryPolicy { retry_policy: Option<RetryPolicy> },
+    AddSchedule { schedule: Schedule },
+    RemoveSchedule { id: u64 },
+    /// Permissionless: requests the payouts of every schedule that is due and resends
+    /// the price requests of timed-out payouts whose backoff has passed.
+    Crank {},
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+/// Payload of a CW20 `Send` to the disburser
+#[cw_serde]
+pub enum ReceiveMsg {
+    /// Deposits the sent CW20 settlement tokens on behalf of the sender
+    Fund {},
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
-pub enum QueryMsg {}
+pub enum QueryMsg {
+    #[returns(ConfigResponse)]
+    Config {},
+    #[returns(StatusResponse)]
+    Status {},
+    #[returns(BudgetResponse)]
+    Budget {},
+    /// Settlement balance held by the contract and the quote budget left for new requests
+    #[returns(AvailableBalanceResponse)]
+    AvailableBalance {},
+    #[returns(PendingPayoutInfo)]
+    PendingPayout { id: u64 },
+    /// Payouts waiting for an oracle price, in ascending id order
+    #[returns(PendingPayoutsResponse)]
+    PendingPayouts {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(SchedulesResponse)]
+    Schedules {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(InitiatorsResponse)]
+    Initiators {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Settlement a payout would receive at `price` with the current balance and budget.
+    /// `amount_in_quote` defaults to the whole available quote budget.
+    #[returns(SimulatePayoutResponse)]
+    SimulatePayout {
+        amount_in_quote: Option<Uint128>,
+        price: Decimal,
+    },
+    #[returns(FundersResponse)]
+    Funders {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(PayoutRecord)]
+    Payout { id: u64 },
+    /// Payout history in ascending id order, or in ascending record block time then id
+    /// when filtered by status or time. `start_after` is the id of the last record seen.
+    /// `since` is inclusive and `until` exclusive, both compared to the record block time.
+    #[returns(PayoutsResponse)]
+    Payouts {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        status: Option<PayoutStatus>,
+        since: Option<Timestamp>,
+        until: Option<Timestamp>,
+    },
+}
+
+#[cw_serde]
+pub enum LifecycleStatus {
+    Active,
+    Paused,
+    Terminated,
+    Expired,
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub settlement_asset: SettlementAsset,
+    pub settlement_asset_limit: Uint128,
+    pub quote_asset_limit: Coin,
+    pub admin: Addr,
+    pub refund_addr: Addr,
+    pub recipient_addr: Addr,
+    pub recipients: Vec<RecipientWeight>,
+    pub oracle_addr: Addr,
+    pub funding_expiration: Expiration,
+    pub payout_duration_bounds: DurationBounds,
+    pub price_band: Option<PriceBand>,
+    pub max_price_age: Option<u64>,
+    pub cached_price_age: Option<u64>,
+    pub quote_decimals: u32,
+    pub settlement_decimals: u32,
+    pub ibc_transfer_timeout: u64,
+    pub keeper_bounty: Option<KeeperBounty>,
+    pub retry_policy: Option<RetryPolicy>,
+}
+
+#[cw_serde]
+pub struct StatusResponse {
+    pub status: LifecycleStatus,
+}
+
+#[cw_serde]
+pub struct BudgetResponse {
+    pub quote_limit: Coin,
+    pub quote_used: Uint128,
+    /// Held by pending payouts, still included in `quote_remaining`
+    pub quote_reserved: Uint128,
+    pub quote_remaining: Uint128,
+    pub settlement_asset: SettlementAsset,
+    pub settlement_limit: Uint128,
+    pub settlement_used: Uint128,
+    pub settlement_remaining: Uint128,
+}
+
+#[cw_serde]
+pub struct AvailableBalanceResponse {
+    pub settlement_balance: Uint128,
+    /// Settlement tokens that can still be paid out, the balance capped by the remaining budget
+    pub settlement_available: Uint128,
+    /// Remaining quote budget less the reservations of pending payouts
+    pub quote_available: Uint128,
+}
+
+#[cw_serde]
+pub struct PendingPayoutInfo {
+    pub id: u64,
+    pub requester: Addr,
+    pub amount_in_quote: Uint128,
+    pub expires_at: Expiration,
+    pub price_band: PriceBand,
+    pub created_at: Timestamp,
+    /// Price requests sent so far
+    pub attempts: u32,
+    /// When the next price request is due after a timeout
+    pub retry_at: Option<Scheduled>,
+}
+
+#[cw_serde]
+pub struct PendingPayoutsResponse {
+    pub payouts: Vec<PendingPayoutInfo>,
+}
+
+#[cw_serde]
+pub struct ScheduleInfo {
+    pub id: u64,
+    pub schedule: Schedule,
+    /// Height or time, in seconds, at which the next period is due
+    pub next_due: u64,
+}
+
+#[cw_serde]
+pub struct SchedulesResponse {
+    pub schedules: Vec<ScheduleInfo>,
+}
+
+#[cw_serde]
+pub struct InitiatorInfo {
+    pub addr: Addr,
+    pub allowance: Option<Uint128>,
+    pub used: Uint128,
+    pub expires_at: Option<Expiration>,
+    pub max_per_request: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct InitiatorsResponse {
+    pub initiators: Vec<InitiatorInfo>,
+}
+
+#[cw_serde]
+pub struct SimulatePayoutResponse {
+    pub amount_in_quote: Uint128,
+    pub settlement_amount: Uint128,
+    /// Quote value delivered, below `amount_in_quote` when the settlement was trimmed
+    pub quote_paid: Uint128,
+    pub recipients: Vec<PayoutShare>,
+}
+
+#[cw_serde]
+pub struct FunderInfo {
+    pub addr: Addr,
+    pub deposited: Uint128,
+}
+
+#[cw_serde]
+pub struct FundersResponse {
+    pub funders: Vec<FunderInfo>,
+    pub total_deposited: Uint128,
+}
+
+#[cw_serde]
+pub enum PayoutStatus {
+    Settled,
+    TimedOut,
+    Cancelled,
+    Failed,
+}
+
+#[cw_serde]
+pub struct PayoutRecord {
+    pub id: u64,
+    pub requester: Addr,
+    pub amount_in_quote: Uint128,
+    pub price: Option<Decimal>,
+    pub settlement_amount: Uint128,
+    pub recipients: Vec<PayoutShare>,
+    pub height: u64,
+    pub time: Timestamp,
+    pub status: PayoutStatus,
+    /// Why a failed payout did not settle
+    pub reason: Option<String>,
+    /// Settlement amount sent back by failed or timed out ICS-20 transfers
+    pub returned: Uint128,
+}
+
+#[cw_serde]
+pub struct PayoutShare {
+    pub addr: String,
+    pub amount: Uint128,
+    /// Set when the share is sent over ICS-20
+    pub channel_id: Option<String>,
+}
+
+#[cw_serde]
+pub struct PayoutsResponse {
+    pub payouts: Vec<PayoutRecord>,
+}
@@ -4,8 +4,76 @@ use cw_utils::Expiration;
 
 #[cw_serde]
 pub struct InstantiateMsg {
-    pub adapters: Option<Vec<Addr>>,
+    pub adapters: Option<Vec<AdapterConfig>>,
     pub admin: Addr,
+    /// Rewards `TimeoutExpiredRequests` callers per expired request of a rewarded
+    /// requester, none when unset
+    pub keeper_bounty: Option<KeeperBounty>,
+}
+
+pub use drip_keeper_bounty::KeeperBounty;
+
+#[cw_serde]
+pub struct PriceKey {
+    pub base: String,
+    pub quote: String,
+}
+
+/// Adapter serving the prices of `pairs`
+#[cw_serde]
+pub struct AdapterConfig {
+    pub adapter: Addr,
+    pub pairs: Vec<PriceKey>,
+}
+
+/// Settles the prices of a pair from several adapters queried in parallel
+#[cw_serde]
+pub struct AggregationConfig {
+    pub adapters: Vec<Addr>,
+    /// Accepted answers needed to settle, at most the number of adapters
+    pub quorum: u32,
+    pub mode: AggregationMode,
+    /// Answers deviating from the median of all answers by more than this fraction of it
+    /// are dropped
+    pub max_deviation: Decimal,
+}
+
+#[cw_serde]
+pub enum AggregationMode {
+    Median,
+    /// Mean of the accepted answers once the `trim` fraction of them, rounded down, is
+    /// dropped at each end; `trim` is below one half
+    TrimmedMean { trim: Decimal },
+}
+
+/// Answers received for an aggregated request and how it settled
+#[cw_serde]
+pub struct AggregationRecord {
+    pub pair: PriceKey,
+    /// In the order received, including those arriving after settlement
+    pub answers: Vec<AdapterAnswer>,
+    /// `None` until a quorum is reached or every adapter has answered
+    pub outcome: Option<AggregationOutcome>,
+}
+
+#[cw_serde]
+pub struct AdapterAnswer {
+    pub adapter: Addr,
+    pub price: Decimal,
+    pub price_timestamp: u64,
+}
+
+#[cw_serde]
+pub enum AggregationOutcome {
+    /// `price_timestamp` is that of the oldest accepted answer
+    Settled {
+        price: Decimal,
+        price_timestamp: u64,
+        accepted: Vec<Addr>,
+    },
+    /// Every adapter answered without a quorum within the deviation; the requesters were
+    /// timed out
+    NoQuorum {},
 }
 
 #[cw_serde]
@@ -16,23 +84,139 @@ pub enum ExecuteMsg {
         expiration: Expiration,
         /// Valid_from – the earliest point in time (or block height) from which the price is still acceptable.
         /// If `None`, only a fresh price will be requested from the adapter.
-        /// If set, the oracle may serve a cached/batched price if it was obtained after this moment.
+        /// If set, the oracle answers at once with the latest price of the pair when it was
+        /// observed at or after this moment, or shares an adapter request sent after it.
         valid_from: Option<Expiration>, // todo: Expiration or relative blocks/time?
+        /// Echoed back as `request_id` in the callback, defaults to the oracle request id
         sequence: Option<u64>,
     },
+    /// Price delivered by the adapter a request was forwarded to
     OnPriceResponse {
         request_id: u64,
         price: Decimal,
         price_timestamp: u64,
     },
+    /// Sends `OnPayoutTimeout` to up to `batch_limit` requesters whose expiration has passed
     TimeoutExpiredRequests {
         batch_limit: u64
     },
-    AddAdapter { adapter: Addr },
+    /// Routes `pairs` to `adapter`, replacing the adapter previously serving them
+    AddAdapter { adapter: Addr, pairs: Vec<PriceKey> },
+    /// Removes every pair routed to `adapter`
     RemoveAdapter { adapter: Addr },
+    /// Aggregates the prices of `pair` over several adapters, taking precedence over its
+    /// single adapter; `None` routes the pair back to that adapter.
+    SetAggregation {
+        pair: PriceKey,
+        aggregation: Option<AggregationConfig>,
+    },
+    /// `None` renounces the admin, freezing the adapter registry.
     UpdateAdmin { admin: Option<String> },
+    /// Replaces the keeper bounty, `None` stops rewarding keepers.
+    UpdateKeeperBounty { keeper_bounty: Option<KeeperBounty> },
+    /// Changes the requesters whose expired requests earn the keeper bounty. Anyone can
+    /// request prices, so timing out the requests of others is not rewarded, or a keeper
+    /// could open short-lived requests only to collect the bounty on them.
+    UpdateRewardedRequesters { add: Vec<String>, remove: Vec<String> },
+}
+
+/// Requests forwarded by the oracle to an adapter, which answers with `OnPriceResponse`
+#[cw_serde]
+pub enum AdapterExecuteMsg {
+    RequestPrice {
+        base: String,
+        quote: String,
+        /// Oracle request id, echoed back as `request_id`
+        sequence: u64,
+        valid_from: Option<Expiration>,
+        expiration: Expiration,
+    },
+}
+
+/// Callbacks the oracle sends to requesters, matching their `RequestPrice` `sequence`
+#[cw_serde]
+pub enum CallbackMsg {
+    OnPayoutResponse {
+        price: Decimal,
+        /// When the price was observed, in seconds since the Unix epoch
+        price_timestamp: u64,
+        request_id: u64,
+    },
+    OnPayoutTimeout {
+        request_id: u64,
+    },
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
-pub enum QueryMsg {}
+pub enum QueryMsg {
+    #[returns(ConfigResponse)]
+    Config {},
+    /// Adapter serving a pair
+    #[returns(Addr)]
+    Adapter { base: String, quote: String },
+    /// Routed pairs in ascending order
+    #[returns(AdaptersResponse)]
+    Adapters {
+        start_after: Option<PriceKey>,
+        limit: Option<u32>,
+    },
+    /// Aggregation of a pair, `None` when served by a single adapter
+    #[returns(Option<AggregationConfig>)]
+    Aggregation { base: String, quote: String },
+    /// Audit record of an aggregated request, `None` for other requests
+    #[returns(Option<AggregationRecord>)]
+    AggregationRecord { request_id: u64 },
+    /// Latest price received for a pair, `None` before the first one
+    #[returns(Option<CachedPriceResponse>)]
+    CachedPrice { base: String, quote: String },
+    /// Latest prices of the pairs in ascending order
+    #[returns(CachedPricesResponse)]
+    CachedPrices {
+        start_after: Option<PriceKey>,
+        limit: Option<u32>,
+    },
+    /// Requesters whose expired requests earn the keeper bounty, in ascending order
+    #[returns(RewardedRequestersResponse)]
+    RewardedRequesters {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub admin: Option<Addr>,
+    pub keeper_bounty: Option<KeeperBounty>,
+}
+
+#[cw_serde]
+pub struct RewardedRequestersResponse {
+    pub requesters: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct AdapterRoute {
+    pub pair: PriceKey,
+    pub adapter: Addr,
+}
+
+#[cw_serde]
+pub struct AdaptersResponse {
+    pub routes: Vec<AdapterRoute>,
+}
+
+#[cw_serde]
+pub struct CachedPriceResponse {
+    pub pair: PriceKey,
+    pub price: Decimal,
+    /// When the price was observed, in seconds since the Unix epoch
+    pub price_timestamp: u64,
+    /// Height the adapter request answered with this price was sent at
+    pub block_height: u64,
+}
+
+#[cw_serde]
+pub struct CachedPricesResponse {
+    pub prices: Vec<CachedPriceResponse>,
+}